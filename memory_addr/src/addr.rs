@@ -1,4 +1,7 @@
 use core::cmp::Ord;
+use core::ptr::NonNull;
+
+use crate::{AddrRange, Pfn};
 
 /// A trait for memory address types.
 ///
@@ -35,6 +38,14 @@ pub trait MemoryAddr:
     }
 
     /// Aligns the address upwards to the given alignment.
+    ///
+    /// The result overflows if the address is in the last (unaligned) block of
+    /// the address space, which panics with overflow checks enabled and wraps
+    /// around to 0 otherwise. Use [`checked_align_up`] or
+    /// [`saturating_align_up`] to handle it.
+    ///
+    /// [`checked_align_up`]: MemoryAddr::checked_align_up
+    /// [`saturating_align_up`]: MemoryAddr::saturating_align_up
     #[inline]
     #[must_use = "this returns a new address, without modifying the original"]
     fn align_up<U>(self, align: U) -> Self
@@ -44,6 +55,49 @@ pub trait MemoryAddr:
         Self::from(crate::align_up(self.into(), align.into()))
     }
 
+    /// Aligns the address upwards to the given alignment, returning `None` if
+    /// the result overflows.
+    ///
+    /// Unlike [`align_up`], which wraps around to 0 near the top of the address
+    /// space (or panics with overflow checks enabled), this method detects the
+    /// overflow, including a result that does not fit in an address type
+    /// narrower than `usize`. The alignment must be a power of two.
+    ///
+    /// [`align_up`]: MemoryAddr::align_up
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn checked_align_up<U>(self, align: U) -> Option<Self>
+    where
+        U: Into<usize>,
+    {
+        let addr = self.into();
+        let align = align.into();
+        let aligned = crate::align_down(addr, align);
+        if aligned == addr {
+            Some(self)
+        } else {
+            aligned.checked_add(align).and_then(checked_from_usize)
+        }
+    }
+
+    /// Aligns the address upwards to the given alignment, returning the
+    /// maximum address (`usize::MAX`, or the maximum of a narrower backing
+    /// integer type) if the result overflows.
+    ///
+    /// Note that the maximum address is not aligned, but it is the closest
+    /// representable address that is not less than the aligned one. It is
+    /// suitable for computing the (exclusive) end of a range that extends to
+    /// the top of the address space. The alignment must be a power of two.
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn saturating_align_up<U>(self, align: U) -> Self
+    where
+        U: Into<usize>,
+    {
+        self.checked_align_up(align)
+            .unwrap_or_else(|| Self::from(usize::MAX))
+    }
+
     /// Returns the offset of the address within the given alignment.
     #[inline]
     #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
@@ -54,6 +108,22 @@ pub trait MemoryAddr:
         crate::align_offset(self.into(), align.into())
     }
 
+    /// Aligns the address downwards to the given alignment, and returns the
+    /// aligned address together with the offset within the alignment.
+    ///
+    /// Equivalent to `(self.align_down(align), self.align_offset(align))`. The
+    /// alignment must be a power of two.
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn align_down_offset<U>(self, align: U) -> (Self, usize)
+    where
+        U: Into<usize>,
+    {
+        let addr = self.into();
+        let offset = crate::align_offset(addr, align.into());
+        (Self::from(addr - offset), offset)
+    }
+
     /// Checks whether the address has the demanded alignment.
     #[inline]
     #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
@@ -64,6 +134,22 @@ pub trait MemoryAddr:
         crate::is_aligned(self.into(), align.into())
     }
 
+    /// Rounds the address up to the smallest multiple of `align` that is
+    /// greater than or equal to it.
+    ///
+    /// This is the general-purpose counterpart to [`align_up`], which only
+    /// works for power-of-two alignments. `align` here can be any non-zero
+    /// value, e.g., 24 or 48.
+    ///
+    /// Returns `None` if `align` is 0 or the result overflows.
+    ///
+    /// [`align_up`]: MemoryAddr::align_up
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn checked_next_multiple_of(self, align: usize) -> Option<Self> {
+        usize::checked_next_multiple_of(self.into(), align).and_then(checked_from_usize)
+    }
+
     /// Aligns the address downwards to 4096 (bytes).
     #[inline]
     #[must_use = "this returns a new address, without modifying the original"]
@@ -72,12 +158,24 @@ pub trait MemoryAddr:
     }
 
     /// Aligns the address upwards to 4096 (bytes).
+    ///
+    /// See [`align_up`](MemoryAddr::align_up) for the overflow behavior.
     #[inline]
     #[must_use = "this returns a new address, without modifying the original"]
     fn align_up_4k(self) -> Self {
         Self::from(crate::align_up(self.into(), crate::PAGE_SIZE_4K))
     }
 
+    /// Aligns the address upwards to 4096 (bytes), saturating at the maximum
+    /// address.
+    ///
+    /// See [`saturating_align_up`](MemoryAddr::saturating_align_up).
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn saturating_align_up_4k(self) -> Self {
+        self.saturating_align_up(crate::PAGE_SIZE_4K)
+    }
+
     /// Returns the offset of the address within a 4K-sized page.
     #[inline]
     #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
@@ -92,6 +190,117 @@ pub trait MemoryAddr:
         crate::is_aligned(self.into(), crate::PAGE_SIZE_4K)
     }
 
+    /// Aligns the address downwards to the alignment given as a const generic
+    /// parameter, which must be a power of two.
+    ///
+    /// The alignment is known at compile time, so the mask can be folded into
+    /// a constant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, MemoryAddr, PAGE_SIZE_4K};
+    ///
+    /// assert_eq!(va!(0x1234).align_down_const::<PAGE_SIZE_4K>(), va!(0x1000));
+    /// ```
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn align_down_const<const ALIGN: usize>(self) -> Self {
+        Self::from(crate::align_down(self.into(), ALIGN))
+    }
+
+    /// Aligns the address upwards to the alignment given as a const generic
+    /// parameter, which must be a power of two.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, MemoryAddr, PAGE_SIZE_4K};
+    ///
+    /// assert_eq!(va!(0x1234).align_up_const::<PAGE_SIZE_4K>(), va!(0x2000));
+    /// assert_eq!(va!(0x2000).align_up_const::<PAGE_SIZE_4K>(), va!(0x2000));
+    /// ```
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn align_up_const<const ALIGN: usize>(self) -> Self {
+        Self::from(crate::align_up(self.into(), ALIGN))
+    }
+
+    /// Checks whether the address is aligned to the alignment given as a const
+    /// generic parameter, which must be a power of two.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, MemoryAddr, PAGE_SIZE_4K};
+    ///
+    /// assert!(va!(0x2000).is_aligned_const::<PAGE_SIZE_4K>());
+    /// assert!(!va!(0x2008).is_aligned_const::<PAGE_SIZE_4K>());
+    /// ```
+    #[inline]
+    #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+    fn is_aligned_const<const ALIGN: usize>(self) -> bool {
+        crate::is_aligned(self.into(), ALIGN)
+    }
+
+    /// Returns the [page frame number](Pfn) of the page containing the
+    /// address, for the page size given as a const generic parameter (a power
+    /// of two).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{pa, MemoryAddr, Pfn, PAGE_SIZE_4K};
+    ///
+    /// assert_eq!(pa!(0x1234_5000).pfn::<PAGE_SIZE_4K>(), Pfn::new(0x12345));
+    /// assert_eq!(pa!(0x1234_5678).pfn::<PAGE_SIZE_4K>(), Pfn::new(0x12345));
+    /// ```
+    #[inline]
+    #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+    fn pfn<const PAGE_SIZE: usize>(self) -> Pfn<PAGE_SIZE> {
+        debug_assert!(PAGE_SIZE.is_power_of_two());
+        Pfn::new(self.into() / PAGE_SIZE)
+    }
+
+    /// Returns the start address of the page with the given
+    /// [page frame number](Pfn).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start address of the page overflows, i.e., the page
+    /// number is out of range for the address type.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{pa, MemoryAddr, Pfn, PhysAddr, PAGE_SIZE_4K};
+    ///
+    /// let pfn = Pfn::<PAGE_SIZE_4K>::new(0x12345);
+    /// assert_eq!(PhysAddr::from_pfn(pfn), pa!(0x1234_5000));
+    /// ```
+    #[inline]
+    #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+    fn from_pfn<const PAGE_SIZE: usize>(pfn: Pfn<PAGE_SIZE>) -> Self {
+        debug_assert!(PAGE_SIZE.is_power_of_two());
+        pfn.as_usize()
+            .checked_mul(PAGE_SIZE)
+            .and_then(checked_from_usize)
+            .expect("overflow in `MemoryAddr::from_pfn`")
+    }
+
+    //
+    // This section contains utility methods for address ranges.
+    //
+
+    /// Checks whether the address is in the given address range.
+    ///
+    /// Equivalent to `range.contains(self)`.
+    #[inline]
+    #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+    fn is_in(self, range: AddrRange<Self>) -> bool {
+        range.contains(self)
+    }
+
     //
     // This section contains utility methods for address arithmetic.
     //
@@ -105,7 +314,9 @@ pub trait MemoryAddr:
     #[must_use = "this returns a new address, without modifying the original"]
     fn offset(self, offset: isize) -> Self {
         // todo: use `strict_add_signed` when it's stable.
-        Self::from(usize::checked_add_signed(self.into(), offset).expect("overflow in `MemoryAddr::offset`"))
+        usize::checked_add_signed(self.into(), offset)
+            .and_then(checked_from_usize)
+            .expect("overflow in `MemoryAddr::offset`")
     }
 
     /// Adds a given offset to the address to get a new address.
@@ -144,7 +355,9 @@ pub trait MemoryAddr:
     #[inline]
     #[must_use = "this returns a new address, without modifying the original"]
     fn add(self, rhs: usize) -> Self {
-        Self::from(usize::checked_add(self.into(), rhs).expect("overflow in `MemoryAddr::add`"))
+        usize::checked_add(self.into(), rhs)
+            .and_then(checked_from_usize)
+            .expect("overflow in `MemoryAddr::add`")
     }
 
     /// Adds a given **unsigned** offset to the address to get a new address.
@@ -164,7 +377,8 @@ pub trait MemoryAddr:
     #[must_use = "this returns a new address, without modifying the original"]
     fn overflowing_add(self, rhs: usize) -> (Self, bool) {
         let (result, overflow) = self.into().overflowing_add(rhs);
-        (Self::from(result), overflow)
+        let addr = Self::from(result);
+        (addr, overflow || addr.into() != result)
     }
 
     /// Adds a given **unsigned** offset to the address to get a new address.
@@ -173,7 +387,16 @@ pub trait MemoryAddr:
     #[inline]
     #[must_use = "this returns a new address, without modifying the original"]
     fn checked_add(self, rhs: usize) -> Option<Self> {
-        usize::checked_add(self.into(), rhs).map(Self::from)
+        usize::checked_add(self.into(), rhs).and_then(checked_from_usize)
+    }
+
+    /// Adds a given **unsigned** offset to the address to get a new address.
+    ///
+    /// Unlike `add`, this method saturates at the maximum address on overflow.
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn saturating_add(self, rhs: usize) -> Self {
+        self.checked_add(rhs).unwrap_or_else(|| Self::from(usize::MAX))
     }
 
     /// Subtracts a given **unsigned** offset from the address to get a new address.
@@ -218,6 +441,15 @@ pub trait MemoryAddr:
         usize::checked_sub(self.into(), rhs).map(Self::from)
     }
 
+    /// Subtracts a given **unsigned** offset from the address to get a new address.
+    ///
+    /// Unlike `sub`, this method saturates at zero on overflow.
+    #[inline]
+    #[must_use = "this returns a new address, without modifying the original"]
+    fn saturating_sub(self, rhs: usize) -> Self {
+        Self::from(usize::saturating_sub(self.into(), rhs))
+    }
+
     /// Subtracts another address from the address to get the offset between them.
     /// 
     /// # Panics
@@ -256,6 +488,27 @@ pub trait MemoryAddr:
     fn checked_sub_addr(self, rhs: Self) -> Option<usize> {
         usize::checked_sub(self.into(), rhs.into())
     }
+
+    /// Gets the absolute distance between two addresses.
+    ///
+    /// Unlike `sub_addr` and `offset_from`, this method never panics, and the
+    /// result does not depend on the order of the two addresses.
+    #[inline]
+    #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+    fn abs_diff(self, other: Self) -> usize {
+        usize::abs_diff(self.into(), other.into())
+    }
+}
+
+/// Converts an `usize` to the address type, returning `None` if it can not be
+/// represented, i.e., if the conversion does not round-trip.
+///
+/// It only matters for address types backed by an integer narrower than
+/// `usize` (see [`def_usize_addr`]), whose `From<usize>` truncates.
+#[inline]
+fn checked_from_usize<A: MemoryAddr>(addr: usize) -> Option<A> {
+    let result = A::from(addr);
+    (result.into() == addr).then_some(result)
 }
 
 /// Implement the `MemoryAddr` trait for any type that is `Copy`, `From<usize>`,
@@ -279,6 +532,61 @@ impl<T> MemoryAddr for T where T: Copy + From<usize> + Into<usize> + Ord {}
 /// - Two `const` methods to convert between the address type and `usize`:
 ///   - `from_usize`, which converts an `usize` to the address type, and
 ///   - `as_usize`, which converts the address type to an `usize`.
+/// - Two `const` methods for checked arithmetic, which can be used in `const`
+///   contexts unlike the methods of [`MemoryAddr`]:
+///   - `checked_add_usize`, which adds an `usize` to the address, and
+///   - `checked_sub_usize`, which subtracts an `usize` from the address.
+///
+/// If the `step_trait` feature is enabled, [`Step`](core::iter::Step) is also
+/// implemented for the address type, so that ranges of addresses like
+/// `va!(0x1000)..va!(0x4000)` can be iterated directly. The methods of `Step`
+/// delegate to the backing integer type. Since `Step` is unstable, this
+/// requires a nightly toolchain, but the crate invoking this macro does not
+/// need to enable `#![feature(step_trait)]` itself. On stable Rust,
+/// [`PageIter`] with a page size of 1 can be used instead, e.g.,
+/// `PageIter::<1, _>::new(start, end)`.
+///
+/// [`PageIter`]: crate::PageIter
+///
+/// If the `bytemuck` feature is enabled, `bytemuck::Pod` and
+/// `bytemuck::Zeroable` are also implemented for the address type, which is
+/// sound since it is a `#[repr(transparent)]` wrapper of a single integer.
+/// This allows zero-copy reads of raw addresses, e.g., from a binary header.
+/// The crate invoking this macro does not need to depend on `bytemuck`
+/// directly.
+///
+/// Similarly, if the `arbitrary` feature is enabled, `arbitrary::Arbitrary` is
+/// implemented for the address type by generating an arbitrary backing
+/// integer, for fuzzing. Since `arbitrary` requires `std`, it is not
+/// implemented on bare-metal targets (i.e., `target_os = "none"`).
+///
+/// ## Custom backing integer type
+///
+/// The backing integer type can be changed from `usize` by writing `$vis type
+/// $name: $ty;` instead, e.g., `pub type GuestPhysAddr: u64;` to model 64-bit
+/// guest physical addresses on a 32-bit host. The generated items are the same
+/// as above, except that the field is of type `$ty`, and the following
+/// methods are generated in addition:
+/// - `from_raw` and `as_raw`, two `const` methods to convert between the
+///   address type and `$ty` losslessly.
+/// - Inherent methods that do the address math in `$ty`, which take precedence
+///   over the methods of [`MemoryAddr`] with the same names: `align_down`,
+///   `align_up`, `checked_align_up`, `align_offset`, `is_aligned`, their `_4k`
+///   variants, `add`, `checked_add`, `wrapping_add`, `saturating_add`, `sub`,
+///   `checked_sub`, `wrapping_sub`, `saturating_sub`, `sub_addr`, and
+///   `checked_sub_addr`. Offsets and alignments that do not fit in `$ty` are
+///   treated as an overflow.
+///
+/// The arithmetic operators panic if the offset does not fit in `$ty`, or the
+/// difference of two addresses does not fit in `usize`. However, the
+/// `usize`-based conversions (`From<usize>`, `Into<usize>`, `from_usize` and
+/// `as_usize`) use `as` casts, so they silently truncate the value when
+/// narrowing, e.g., converting a `u64`-backed address above 4GiB to `usize` on
+/// a 32-bit target. The same applies to the other methods of [`MemoryAddr`]
+/// and to generic code built on it (e.g., [`AddrRange`]), which work on
+/// `usize`. Their checked (and saturating) methods, as well as
+/// `checked_add_usize` and `checked_sub_usize`, do detect a result that does
+/// not fit in `$ty` and treat it as an overflow.
 ///
 /// # Example
 ///
@@ -299,6 +607,31 @@ impl<T> MemoryAddr for T where T: Copy + From<usize> + Into<usize> + Ord {}
 /// assert_eq!(EXAMPLE.align_up_4k(), ExampleAddr::from_usize(0x2000));
 /// # }
 /// ```
+///
+/// With a custom backing integer type:
+///
+/// ```
+/// use memory_addr::{def_usize_addr, MemoryAddr};
+///
+/// def_usize_addr! {
+///     /// A guest physical address, which is always 64-bit.
+///     #[derive(Debug)]
+///     pub type GuestPhysAddr: u64;
+/// }
+///
+/// # fn main() {
+/// let gpa = GuestPhysAddr::from_raw(0x1234_5678);
+/// assert_eq!(gpa.as_raw(), 0x1234_5678u64);
+/// assert_eq!(gpa.as_usize(), 0x1234_5678);
+/// assert_eq!(gpa.align_down_4k(), GuestPhysAddr::from_raw(0x1234_5000));
+/// assert_eq!(core::mem::size_of::<GuestPhysAddr>(), 8);
+///
+/// // Addresses above 4GiB are handled on 32-bit hosts as well.
+/// let high = GuestPhysAddr::from_raw(0x1_0000_0123);
+/// assert_eq!(high.align_down_4k(), GuestPhysAddr::from_raw(0x1_0000_0000));
+/// assert_eq!(high.add(0x1000), GuestPhysAddr::from_raw(0x1_0000_1123));
+/// # }
+/// ```
 #[macro_export]
 macro_rules! def_usize_addr {
     (
@@ -312,31 +645,274 @@ macro_rules! def_usize_addr {
         $(#[$meta])*
         pub struct $name(usize);
 
+        $crate::def_usize_addr!(@impl $name, usize);
+
+        $crate::def_usize_addr!($($tt)*);
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis type $name:ident: $ty:ty;
+
+        $($tt:tt)*
+    ) => {
+        #[repr(transparent)]
+        #[derive(Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq)]
+        $(#[$meta])*
+        pub struct $name($ty);
+
+        impl $name {
+            #[doc = concat!("Converts a raw `", stringify!($ty), "` to an [`", stringify!($name), "`].")]
+            #[inline]
+            pub const fn from_raw(addr: $ty) -> Self {
+                Self(addr)
+            }
+
+            #[doc = concat!("Converts an [`", stringify!($name), "`] to a raw `", stringify!($ty), "`.")]
+            #[inline]
+            pub const fn as_raw(self) -> $ty {
+                self.0
+            }
+        }
+
+        $crate::def_usize_addr!(@raw $name, $ty);
+        $crate::def_usize_addr!(@impl $name, $ty);
+
+        $crate::def_usize_addr!($($tt)*);
+    };
+    (@raw $name:ident, $ty:ty) => {
+        // Address math done in the backing integer type, which shadows the
+        // `usize`-based methods of `MemoryAddr` with the same names.
+        impl $name {
+            /// Returns the mask of the given alignment in the backing integer
+            /// type, saturating if the alignment does not fit in it.
+            #[inline]
+            fn __align_mask(align: usize) -> $ty {
+                <$ty>::try_from(align - 1).unwrap_or(<$ty>::MAX)
+            }
+
+            /// Aligns the address downwards to the given alignment.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn align_down<U: Into<usize>>(self, align: U) -> Self {
+                Self(self.0 & !Self::__align_mask(align.into()))
+            }
+
+            /// Aligns the address upwards to the given alignment.
+            ///
+            /// See `MemoryAddr::align_up` for the overflow behavior.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn align_up<U: Into<usize>>(self, align: U) -> Self {
+                let mask = Self::__align_mask(align.into());
+                Self((self.0 + mask) & !mask)
+            }
+
+            /// Aligns the address upwards to the given alignment, returning
+            /// `None` if the result overflows.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn checked_align_up<U: Into<usize>>(self, align: U) -> Option<Self> {
+                let mask = Self::__align_mask(align.into());
+                match self.0.checked_add(mask) {
+                    Some(addr) => Some(Self(addr & !mask)),
+                    None => None,
+                }
+            }
+
+            /// Returns the offset of the address within the given alignment.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn align_offset<U: Into<usize>>(self, align: U) -> usize {
+                // The offset is less than the alignment, so it fits in `usize`.
+                (self.0 & Self::__align_mask(align.into())) as usize
+            }
+
+            /// Checks whether the address has the demanded alignment.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn is_aligned<U: Into<usize>>(self, align: U) -> bool {
+                self.0 & Self::__align_mask(align.into()) == 0
+            }
+
+            /// Aligns the address downwards to 4096 (bytes).
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn align_down_4k(self) -> Self {
+                self.align_down($crate::PAGE_SIZE_4K)
+            }
+
+            /// Aligns the address upwards to 4096 (bytes).
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn align_up_4k(self) -> Self {
+                self.align_up($crate::PAGE_SIZE_4K)
+            }
+
+            /// Returns the offset of the address within a 4K-sized page.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn align_offset_4k(self) -> usize {
+                self.align_offset($crate::PAGE_SIZE_4K)
+            }
+
+            /// Checks whether the address is 4K-aligned.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn is_aligned_4k(self) -> bool {
+                self.is_aligned($crate::PAGE_SIZE_4K)
+            }
+
+            /// Adds a given **unsigned** offset to the address, panicking on
+            /// overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn add(self, rhs: usize) -> Self {
+                self.checked_add(rhs).expect("overflow in `MemoryAddr::add`")
+            }
+
+            /// Adds a given **unsigned** offset to the address, returning
+            /// `None` on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn checked_add(self, rhs: usize) -> Option<Self> {
+                match <$ty>::try_from(rhs) {
+                    Ok(rhs) => match self.0.checked_add(rhs) {
+                        Some(addr) => Some(Self(addr)),
+                        None => None,
+                    },
+                    Err(_) => None,
+                }
+            }
+
+            /// Adds a given **unsigned** offset to the address, wrapping
+            /// around on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn wrapping_add(self, rhs: usize) -> Self {
+                // Truncating the offset keeps it the same modulo 2^N.
+                Self(self.0.wrapping_add(rhs as $ty))
+            }
+
+            /// Adds a given **unsigned** offset to the address, saturating at
+            /// the maximum address on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn saturating_add(self, rhs: usize) -> Self {
+                self.checked_add(rhs).unwrap_or(Self(<$ty>::MAX))
+            }
+
+            /// Subtracts a given **unsigned** offset from the address,
+            /// panicking on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn sub(self, rhs: usize) -> Self {
+                self.checked_sub(rhs).expect("overflow in `MemoryAddr::sub`")
+            }
+
+            /// Subtracts a given **unsigned** offset from the address,
+            /// returning `None` on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn checked_sub(self, rhs: usize) -> Option<Self> {
+                match <$ty>::try_from(rhs) {
+                    Ok(rhs) => match self.0.checked_sub(rhs) {
+                        Some(addr) => Some(Self(addr)),
+                        None => None,
+                    },
+                    Err(_) => None,
+                }
+            }
+
+            /// Subtracts a given **unsigned** offset from the address,
+            /// wrapping around on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn wrapping_sub(self, rhs: usize) -> Self {
+                // Truncating the offset keeps it the same modulo 2^N.
+                Self(self.0.wrapping_sub(rhs as $ty))
+            }
+
+            /// Subtracts a given **unsigned** offset from the address,
+            /// saturating at zero on overflow.
+            #[inline]
+            #[must_use = "this returns a new address, without modifying the original"]
+            pub fn saturating_sub(self, rhs: usize) -> Self {
+                self.checked_sub(rhs).unwrap_or(Self(0))
+            }
+
+            /// Subtracts another address from the address to get the offset
+            /// between them, panicking if it is negative or does not fit in
+            /// `usize`.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn sub_addr(self, rhs: Self) -> usize {
+                self.checked_sub_addr(rhs)
+                    .expect("overflow in `MemoryAddr::sub_addr`")
+            }
+
+            /// Subtracts another address from the address to get the offset
+            /// between them, returning `None` if it is negative or does not
+            /// fit in `usize`.
+            #[inline]
+            #[must_use = "this function has no side effects, so it can be removed if the return value is not used"]
+            pub fn checked_sub_addr(self, rhs: Self) -> Option<usize> {
+                match self.0.checked_sub(rhs.0) {
+                    Some(offset) => usize::try_from(offset).ok(),
+                    None => None,
+                }
+            }
+        }
+    };
+    (@impl $name:ident, $ty:ty) => {
         impl $name {
             #[doc = concat!("Converts an `usize` to an [`", stringify!($name), "`].")]
             #[inline]
             pub const fn from_usize(addr: usize) -> Self {
-                Self(addr)
+                Self(addr as $ty)
             }
 
             #[doc = concat!("Converts an [`", stringify!($name), "`] to an `usize`.")]
             #[inline]
             pub const fn as_usize(self) -> usize {
-                self.0
+                self.0 as usize
+            }
+
+            #[doc = concat!("Adds an `usize` to the [`", stringify!($name), "`], returning `None` on overflow.")]
+            #[inline]
+            pub const fn checked_add_usize(self, rhs: usize) -> Option<Self> {
+                if (rhs as $ty) as usize != rhs {
+                    return None;
+                }
+                match self.0.checked_add(rhs as $ty) {
+                    Some(addr) => Some(Self(addr)),
+                    None => None,
+                }
+            }
+
+            #[doc = concat!("Subtracts an `usize` from the [`", stringify!($name), "`], returning `None` on overflow.")]
+            #[inline]
+            pub const fn checked_sub_usize(self, rhs: usize) -> Option<Self> {
+                if (rhs as $ty) as usize != rhs {
+                    return None;
+                }
+                match self.0.checked_sub(rhs as $ty) {
+                    Some(addr) => Some(Self(addr)),
+                    None => None,
+                }
             }
         }
 
         impl From<usize> for $name {
             #[inline]
             fn from(addr: usize) -> Self {
-                Self(addr)
+                Self(addr as $ty)
             }
         }
 
         impl From<$name> for usize {
             #[inline]
             fn from(addr: $name) -> usize {
-                addr.0
+                addr.0 as usize
             }
         }
 
@@ -344,14 +920,14 @@ macro_rules! def_usize_addr {
             type Output = Self;
             #[inline]
             fn add(self, rhs: usize) -> Self {
-                Self(self.0 + rhs)
+                Self(self.0 + <$ty>::try_from(rhs).expect("offset out of range of the address type"))
             }
         }
 
         impl core::ops::AddAssign<usize> for $name {
             #[inline]
             fn add_assign(&mut self, rhs: usize) {
-                self.0 += rhs;
+                self.0 += <$ty>::try_from(rhs).expect("offset out of range of the address type");
             }
         }
 
@@ -359,14 +935,14 @@ macro_rules! def_usize_addr {
             type Output = Self;
             #[inline]
             fn sub(self, rhs: usize) -> Self {
-                Self(self.0 - rhs)
+                Self(self.0 - <$ty>::try_from(rhs).expect("offset out of range of the address type"))
             }
         }
 
         impl core::ops::SubAssign<usize> for $name {
             #[inline]
             fn sub_assign(&mut self, rhs: usize) {
-                self.0 -= rhs;
+                self.0 -= <$ty>::try_from(rhs).expect("offset out of range of the address type");
             }
         }
 
@@ -374,15 +950,96 @@ macro_rules! def_usize_addr {
             type Output = usize;
             #[inline]
             fn sub(self, rhs: $name) -> usize {
-                self.0 - rhs.0
+                usize::try_from(self.0 - rhs.0).expect("offset out of range of `usize`")
             }
         }
 
-        $crate::def_usize_addr!($($tt)*);
+        $crate::__impl_addr_step!($name, $ty);
+        $crate::__impl_addr_bytemuck!($name);
+        $crate::__impl_addr_arbitrary!($name, $ty);
     };
     () => {};
 }
 
+#[cfg(feature = "step_trait")]
+#[doc(hidden)]
+#[macro_export]
+#[allow_internal_unstable(step_trait)]
+macro_rules! __impl_addr_step {
+    ($name:ident, $ty:ty) => {
+        impl core::iter::Step for $name {
+            #[inline]
+            fn steps_between(start: &Self, end: &Self) -> (usize, Option<usize>) {
+                <$ty as core::iter::Step>::steps_between(&start.0, &end.0)
+            }
+
+            #[inline]
+            fn forward_checked(start: Self, count: usize) -> Option<Self> {
+                <$ty as core::iter::Step>::forward_checked(start.0, count).map(Self)
+            }
+
+            #[inline]
+            fn backward_checked(start: Self, count: usize) -> Option<Self> {
+                <$ty as core::iter::Step>::backward_checked(start.0, count).map(Self)
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "step_trait"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_addr_step {
+    ($name:ident, $ty:ty) => {};
+}
+
+#[cfg(feature = "bytemuck")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_addr_bytemuck {
+    ($name:ident) => {
+        // SAFETY: the address type is a `#[repr(transparent)]` wrapper of an
+        // integer, which is `Pod`.
+        unsafe impl $crate::__bytemuck::Zeroable for $name {}
+        unsafe impl $crate::__bytemuck::Pod for $name {}
+    };
+}
+
+#[cfg(not(feature = "bytemuck"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_addr_bytemuck {
+    ($name:ident) => {};
+}
+
+#[cfg(all(feature = "arbitrary", not(target_os = "none")))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_addr_arbitrary {
+    ($name:ident, $ty:ty) => {
+        impl<'a> $crate::__arbitrary::Arbitrary<'a> for $name {
+            #[inline]
+            fn arbitrary(
+                u: &mut $crate::__arbitrary::Unstructured<'a>,
+            ) -> $crate::__arbitrary::Result<Self> {
+                <$ty as $crate::__arbitrary::Arbitrary<'a>>::arbitrary(u).map(Self)
+            }
+
+            #[inline]
+            fn size_hint(depth: usize) -> (usize, Option<usize>) {
+                <$ty as $crate::__arbitrary::Arbitrary<'a>>::size_hint(depth)
+            }
+        }
+    };
+}
+
+#[cfg(not(all(feature = "arbitrary", not(target_os = "none"))))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_addr_arbitrary {
+    ($name:ident, $ty:ty) => {};
+}
+
 /// Creates implementations for the [`Debug`](core::fmt::Debug),
 /// [`LowerHex`](core::fmt::LowerHex), and [`UpperHex`](core::fmt::UpperHex)
 /// traits for the given address types defined by the [`def_usize_addr`].
@@ -473,6 +1130,18 @@ impl VirtAddr {
         Self(ptr as usize)
     }
 
+    /// Creates a new virtual address from a reference.
+    #[inline]
+    pub fn from_ref<T>(r: &T) -> Self {
+        Self::from_ptr_of(r as *const T)
+    }
+
+    /// Creates a new virtual address from a mutable reference.
+    #[inline]
+    pub fn from_mut<T>(r: &mut T) -> Self {
+        Self::from_mut_ptr_of(r as *mut T)
+    }
+
     /// Converts the virtual address to a raw pointer.
     #[inline]
     pub const fn as_ptr(self) -> *const u8 {
@@ -497,6 +1166,15 @@ impl VirtAddr {
     pub const fn as_mut_ptr_of<T>(self) -> *mut T {
         self.0 as *mut T
     }
+
+    /// Converts the virtual address to a [`NonNull`] pointer of a specific
+    /// type.
+    ///
+    /// Returns `None` if the address is 0.
+    #[inline]
+    pub fn as_non_null<T>(self) -> Option<NonNull<T>> {
+        NonNull::new(self.as_mut_ptr_of::<T>())
+    }
 }
 
 /// Alias for [`PhysAddr::from_usize`].
@@ -528,9 +1206,21 @@ mod test {
         pub type AnotherAddr;
     }
 
+    def_usize_addr! {
+        /// An example address type backed by `u64`.
+        pub type WideAddr: u64;
+        /// An example address type backed by `u32`.
+        pub type NarrowAddr: u32;
+        /// An example address type wider than `usize` on any host.
+        pub type U128Addr: u128;
+    }
+
     def_usize_addr_formatter! {
         ExampleAddr = "EA:{}";
         AnotherAddr = "AA:{}";
+        WideAddr = "WA:{}";
+        NarrowAddr = "NA:{}";
+        U128Addr = "UA:{}";
     }
 
     #[test]
@@ -581,6 +1271,126 @@ mod test {
         assert!(example1 != example2);
     }
 
+    #[test]
+    pub fn test_addr_const_checked_arithmetic() {
+        const BASE: ExampleAddr = ExampleAddr::from_usize(0x1000);
+        const ADDED: Option<ExampleAddr> = BASE.checked_add_usize(0x234);
+        const SUBBED: Option<ExampleAddr> = BASE.checked_sub_usize(0x1000);
+
+        assert_eq!(ADDED, Some(ExampleAddr::from_usize(0x1234)));
+        assert_eq!(SUBBED, Some(ExampleAddr::from_usize(0)));
+        assert_eq!(const { BASE.checked_sub_usize(0x1001) }, None);
+        assert_eq!(
+            const { ExampleAddr::from_usize(usize::MAX).checked_add_usize(1) },
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_addr_custom_backing_type() {
+        assert_eq!(size_of::<WideAddr>(), 8);
+        assert_eq!(size_of::<NarrowAddr>(), 4);
+
+        let wide = WideAddr::from_raw(0x1234_5678);
+        assert_eq!(wide.as_raw(), 0x1234_5678u64);
+        assert_eq!(wide.as_usize(), 0x1234_5678);
+        assert_eq!(WideAddr::from_usize(0x1234_5678), wide);
+        assert_eq!(wide.align_down_4k(), WideAddr::from_raw(0x1234_5000));
+        assert_eq!(wide + 0x8, WideAddr::from_raw(0x1234_5680));
+        assert_eq!(wide - WideAddr::from_raw(0x1234_0000), 0x5678);
+        assert_eq!(
+            wide.checked_add_usize(0x1000),
+            Some(WideAddr::from_raw(0x1234_6678))
+        );
+        assert_eq!(WideAddr::from_raw(u64::MAX).checked_add_usize(1), None);
+
+        let narrow = NarrowAddr::from_raw(0xffff_f000);
+        assert_eq!(narrow.as_usize(), 0xffff_f000);
+        assert_eq!(narrow.checked_add_usize(0x1000), None);
+        assert_eq!(narrow.checked_add(0x1000), None);
+        assert_eq!(
+            narrow.saturating_add(0x1000),
+            NarrowAddr::from_raw(u32::MAX)
+        );
+        assert!(narrow.overflowing_add(0x1000).1);
+        assert_eq!(
+            narrow.checked_add(0xfff),
+            Some(NarrowAddr::from_raw(u32::MAX))
+        );
+        #[cfg(target_pointer_width = "64")]
+        {
+            // Offsets wider than `u32` are not truncated.
+            let zero = NarrowAddr::from_usize(0);
+            assert_eq!(zero.checked_add_usize(1 << 32), None);
+            assert_eq!(zero.checked_add(1 << 32), None);
+            assert_eq!(narrow.checked_sub_usize((1 << 32) + 1), None);
+            assert_eq!(zero.checked_next_multiple_of(1 << 32), Some(zero));
+            assert_eq!(narrow.checked_next_multiple_of(1 << 32), None);
+        }
+        assert_eq!(narrow.align_up_4k(), narrow);
+        assert_eq!(format!("{:?}", narrow), "NA:0xfffff000");
+        assert_eq!(format!("{:x}", wide), "WA:0x12345678");
+    }
+
+    #[test]
+    #[cfg(feature = "step_trait")]
+    pub fn test_addr_step() {
+        let addrs = (va!(0x1000)..va!(0x1004)).collect::<Vec<_>>();
+        assert_eq!(addrs, [va!(0x1000), va!(0x1001), va!(0x1002), va!(0x1003)]);
+
+        let pages = (va!(0x1000)..va!(0x4000))
+            .step_by(0x1000)
+            .collect::<Vec<_>>();
+        let stable = crate::PageIter::<0x1000, _>::new(va!(0x1000), va!(0x4000)).unwrap();
+        assert_eq!(pages, stable.collect::<Vec<_>>());
+
+        let wide = (WideAddr::from_raw(0x10)..=WideAddr::from_raw(0x12)).rev();
+        assert_eq!(
+            wide.collect::<Vec<_>>(),
+            [
+                WideAddr::from_raw(0x12),
+                WideAddr::from_raw(0x11),
+                WideAddr::from_raw(0x10)
+            ]
+        );
+
+        use core::iter::Step;
+        let max = ExampleAddr::from_usize(usize::MAX);
+        assert_eq!(Step::forward_checked(max, 1), None);
+        assert_eq!(
+            Step::backward_checked(max, usize::MAX),
+            Some(ExampleAddr::from_usize(0))
+        );
+        assert_eq!(
+            Step::steps_between(
+                &ExampleAddr::from_usize(0x10),
+                &ExampleAddr::from_usize(0x20)
+            ),
+            (0x10, Some(0x10))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "bytemuck")]
+    pub fn test_addr_bytemuck() {
+        let bytes = 0x1234_5678usize.to_ne_bytes();
+        let addr: VirtAddr = bytemuck::pod_read_unaligned(&bytes);
+        assert_eq!(addr, va!(0x1234_5678));
+        assert_eq!(bytemuck::bytes_of(&addr), bytes);
+
+        let header = [pa!(0x1000), pa!(0x2000)];
+        let raw: &[u8] = bytemuck::cast_slice(&header);
+        assert_eq!(raw.len(), 2 * core::mem::size_of::<usize>());
+        assert_eq!(bytemuck::cast_slice::<u8, PhysAddr>(raw), header);
+
+        let wide: WideAddr = bytemuck::pod_read_unaligned(&0xdead_beefu64.to_ne_bytes());
+        assert_eq!(wide, WideAddr::from_raw(0xdead_beef));
+        assert_eq!(
+            <NarrowAddr as bytemuck::Zeroable>::zeroed(),
+            NarrowAddr::from_raw(0)
+        );
+    }
+
     #[test]
     pub fn test_addr_fmt() {
         assert_eq!(format!("{:?}", ExampleAddr::from(0x1abc)), "EA:0x1abc");
@@ -607,6 +1417,152 @@ mod test {
             ExampleAddr::from_usize(base).align_up(alignment),
             ExampleAddr::from_usize(base)
         );
+        assert_eq!(
+            addr.align_down_offset(alignment),
+            (ExampleAddr::from_usize(base), offset)
+        );
+
+        let addr = ExampleAddr::from_usize(0x12345678);
+        assert_eq!(
+            addr.align_down_offset(0x1000usize),
+            (ExampleAddr::from_usize(0x12345000), 0x678)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(0x12345000).align_down_offset(0x1000usize),
+            (ExampleAddr::from_usize(0x12345000), 0)
+        );
+    }
+
+    #[test]
+    pub fn test_checked_align_up() {
+        let align = 0x1000usize;
+        assert_eq!(
+            ExampleAddr::from_usize(0x1234).checked_align_up(align),
+            Some(ExampleAddr::from_usize(0x2000))
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(0x2000).checked_align_up(align),
+            Some(ExampleAddr::from_usize(0x2000))
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX - 1).checked_align_up(align),
+            None
+        );
+        // The last page is already aligned, so no overflow happens.
+        let last_page = ExampleAddr::from_usize(usize::MAX - 0xfff);
+        assert_eq!(last_page.checked_align_up(align), Some(last_page));
+
+        // The result does not fit in a narrow address type.
+        let narrow = NarrowAddr::from_raw(0xffff_f001);
+        assert_eq!(narrow.checked_align_up(align), None);
+        assert_eq!(
+            NarrowAddr::from_raw(0xffff_e001).checked_align_up(align),
+            Some(NarrowAddr::from_raw(0xffff_f000))
+        );
+    }
+
+    #[test]
+    pub fn test_saturating_align_up() {
+        let align = 0x1000usize;
+        assert_eq!(
+            ExampleAddr::from_usize(0x1234).saturating_align_up(align),
+            ExampleAddr::from_usize(0x2000)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX - 1).saturating_align_up(align),
+            ExampleAddr::from_usize(usize::MAX)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX).saturating_align_up(align),
+            ExampleAddr::from_usize(usize::MAX)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(0x1234).saturating_align_up_4k(),
+            ExampleAddr::from_usize(0x2000)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX - 1).saturating_align_up_4k(),
+            ExampleAddr::from_usize(usize::MAX)
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX).saturating_align_up_4k(),
+            ExampleAddr::from_usize(usize::MAX)
+        );
+        let last_page = ExampleAddr::from_usize(usize::MAX - 0xfff);
+        assert_eq!(last_page.saturating_align_up_4k(), last_page);
+
+        // A narrow address type saturates at its own maximum.
+        let narrow = NarrowAddr::from_raw(0xffff_f001);
+        assert_eq!(
+            narrow.saturating_align_up(align),
+            NarrowAddr::from_raw(u32::MAX)
+        );
+        assert_eq!(
+            narrow.saturating_align_up_4k(),
+            NarrowAddr::from_raw(u32::MAX)
+        );
+        assert_eq!(
+            NarrowAddr::from_raw(0xffff_e001).saturating_align_up_4k(),
+            NarrowAddr::from_raw(0xffff_f000)
+        );
+    }
+
+    #[test]
+    pub fn test_next_multiple_of() {
+        let align = 24usize;
+
+        assert_eq!(
+            ExampleAddr::from_usize(0).checked_next_multiple_of(align),
+            Some(ExampleAddr::from_usize(0))
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(1).checked_next_multiple_of(align),
+            Some(ExampleAddr::from_usize(24))
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(48).checked_next_multiple_of(align),
+            Some(ExampleAddr::from_usize(48))
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(49).checked_next_multiple_of(align),
+            Some(ExampleAddr::from_usize(72))
+        );
+        // `align_up` gives a wrong result for non-power-of-two alignments.
+        assert_eq!(
+            ExampleAddr::from_usize(25).checked_next_multiple_of(align),
+            Some(ExampleAddr::from_usize(48))
+        );
+        assert_ne!(
+            ExampleAddr::from_usize(25).align_up(align),
+            ExampleAddr::from_usize(48)
+        );
+
+        assert_eq!(
+            ExampleAddr::from_usize(0x1234).checked_next_multiple_of(0),
+            None
+        );
+        assert_eq!(
+            ExampleAddr::from_usize(usize::MAX).checked_next_multiple_of(align),
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_addr_is_in() {
+        let range = AddrRange::new(
+            ExampleAddr::from_usize(0x1000),
+            ExampleAddr::from_usize(0x2000),
+        );
+        assert!(!ExampleAddr::from_usize(0x0fff).is_in(range));
+        assert!(ExampleAddr::from_usize(0x1000).is_in(range));
+        assert!(ExampleAddr::from_usize(0x1fff).is_in(range));
+        assert!(!ExampleAddr::from_usize(0x2000).is_in(range));
+
+        let empty = AddrRange::new(
+            ExampleAddr::from_usize(0x1000),
+            ExampleAddr::from_usize(0x1000),
+        );
+        assert!(!ExampleAddr::from_usize(0x1000).is_in(empty));
     }
 
     #[test]
@@ -707,6 +1663,53 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn test_addr_saturating_arithmetic() {
+        let low_addr = ExampleAddr::from_usize(0x100usize);
+        let high_addr = ExampleAddr::from_usize(usize::MAX - 0x100usize);
+        let small_offset = 0x50usize;
+        let large_offset = 0x200usize;
+
+        assert_eq!(
+            low_addr.saturating_sub(small_offset),
+            low_addr.wrapping_sub(small_offset)
+        );
+        assert_eq!(
+            low_addr.saturating_sub(large_offset),
+            ExampleAddr::from_usize(0)
+        );
+        assert_eq!(
+            high_addr.saturating_add(small_offset),
+            high_addr.wrapping_add(small_offset)
+        );
+        assert_eq!(
+            high_addr.saturating_add(large_offset),
+            ExampleAddr::from_usize(usize::MAX)
+        );
+
+        let max_addr = ExampleAddr::from_usize(usize::MAX);
+        let zero_addr = ExampleAddr::from_usize(0);
+        assert_eq!(max_addr.saturating_add(1), max_addr);
+        assert_eq!(max_addr.saturating_sub(usize::MAX), zero_addr);
+        assert_eq!(zero_addr.saturating_sub(1), zero_addr);
+        assert_eq!(zero_addr.saturating_add(usize::MAX), max_addr);
+    }
+
+    #[test]
+    pub fn test_addr_abs_diff() {
+        let low_addr = ExampleAddr::from_usize(0x100usize);
+        let high_addr = ExampleAddr::from_usize(0x1234usize);
+
+        assert_eq!(high_addr.abs_diff(low_addr), 0x1134);
+        assert_eq!(low_addr.abs_diff(high_addr), 0x1134);
+        assert_eq!(low_addr.abs_diff(low_addr), 0);
+
+        let zero_addr = ExampleAddr::from_usize(0);
+        let max_addr = ExampleAddr::from_usize(usize::MAX);
+        assert_eq!(max_addr.abs_diff(zero_addr), usize::MAX);
+        assert_eq!(zero_addr.abs_diff(max_addr), usize::MAX);
+    }
+
     #[test]
     #[should_panic]
     pub fn test_addr_offset_overflow() {
@@ -735,6 +1738,91 @@ mod test {
         let _ = addr.add(1);
     }
 
+    #[test]
+    #[should_panic]
+    pub fn test_addr_narrow_add_overflow() {
+        let addr = NarrowAddr::from_raw(u32::MAX);
+        let _ = addr.add(1);
+    }
+
+    #[test]
+    pub fn test_addr_wider_than_usize() {
+        // Stands in for a `u64`-backed address on a 32-bit host: the address
+        // math is done in the backing type, without truncating to `usize`.
+        let high = 1u128 << 64;
+        let addr = U128Addr::from_raw(high + 0x1234);
+        assert_eq!(addr.align_down_4k(), U128Addr::from_raw(high + 0x1000));
+        assert_eq!(addr.align_up_4k(), U128Addr::from_raw(high + 0x2000));
+        assert_eq!(addr.align_offset_4k(), 0x234);
+        assert!(!addr.is_aligned_4k());
+        assert!(U128Addr::from_raw(high).is_aligned(0x10000usize));
+        assert_eq!(
+            addr.checked_align_up(0x1000usize),
+            Some(U128Addr::from_raw(high + 0x2000))
+        );
+        assert_eq!(
+            U128Addr::from_raw(u128::MAX).checked_align_up(0x1000usize),
+            None
+        );
+
+        assert_eq!(addr.add(0x10), U128Addr::from_raw(high + 0x1244));
+        assert_eq!(addr.sub(0x234), U128Addr::from_raw(high + 0x1000));
+        assert_eq!(addr + 0x10, U128Addr::from_raw(high + 0x1244));
+        assert_eq!(
+            addr.checked_add(0x10),
+            Some(U128Addr::from_raw(high + 0x1244))
+        );
+        assert_eq!(U128Addr::from_raw(u128::MAX).checked_add(1), None);
+        assert_eq!(addr.checked_sub(0x1235), Some(U128Addr::from_raw(high - 1)));
+        assert_eq!(
+            U128Addr::from_raw(u128::MAX).saturating_add(1),
+            U128Addr::from_raw(u128::MAX)
+        );
+        assert_eq!(
+            U128Addr::from_raw(1).saturating_sub(2),
+            U128Addr::from_raw(0)
+        );
+        assert_eq!(
+            U128Addr::from_raw(0).wrapping_sub(1),
+            U128Addr::from_raw(u128::MAX)
+        );
+
+        assert_eq!(addr.sub_addr(U128Addr::from_raw(high)), 0x1234);
+        assert_eq!(addr - U128Addr::from_raw(high), 0x1234);
+        assert_eq!(addr.checked_sub_addr(U128Addr::from_raw(0)), None);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_addr_wider_than_usize_sub_addr_overflow() {
+        let _ = U128Addr::from_raw(1 << 64) - U128Addr::from_raw(0);
+    }
+
+    #[test]
+    #[should_panic]
+    pub fn test_addr_narrow_add_op_truncation() {
+        // The offset does not fit in `u32`, and must not be truncated to 0.
+        let mut addr = NarrowAddr::from_raw(0);
+        addr += u32::MAX as usize + 1;
+    }
+
+    #[test]
+    pub fn test_addr_narrow_native_math() {
+        let addr = NarrowAddr::from_raw(0xffff_f123);
+        assert_eq!(addr.align_down_4k(), NarrowAddr::from_raw(0xffff_f000));
+        assert_eq!(addr.checked_align_up(0x1000usize), None);
+        assert_eq!(addr.align_offset(0x1000usize), 0x123);
+        assert_eq!(addr.wrapping_add(0xedd), NarrowAddr::from_raw(0));
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(addr.checked_add(1 << 32), None);
+            assert_eq!(addr.checked_sub(1 << 32), None);
+            assert_eq!(addr.align_down(1usize << 32), NarrowAddr::from_raw(0));
+            assert!(!addr.is_aligned(1usize << 32));
+            assert!(NarrowAddr::from_raw(0).is_aligned(1usize << 32));
+        }
+    }
+
     #[test]
     #[should_panic]
     pub fn test_addr_sub_underflow() {
@@ -784,4 +1872,85 @@ mod test {
         assert_eq!(a[2], 0xdeadbeef);
         assert_eq!(a[3], 0xcafebabe);
     }
+
+    #[test]
+    pub fn test_virt_addr_from_ref() {
+        let mut a = [0u32; 4];
+        assert_eq!(
+            VirtAddr::from_ref(&a),
+            VirtAddr::from_ptr_of(&a as *const _)
+        );
+        assert_eq!(
+            VirtAddr::from_ref(&a[1]),
+            VirtAddr::from_ptr_of(&a[1] as *const u32)
+        );
+        let ptr = &mut a[2] as *mut u32;
+        assert_eq!(
+            VirtAddr::from_mut(&mut a[2]),
+            VirtAddr::from_mut_ptr_of(ptr)
+        );
+        assert_eq!(
+            VirtAddr::from_mut(&mut a[2]).as_usize() - VirtAddr::from_ref(&a[0]).as_usize(),
+            2 * size_of::<u32>()
+        );
+    }
+
+    #[test]
+    pub fn test_virt_addr_non_null() {
+        let mut a = 0x1234usize;
+        let va = VirtAddr::from_mut_ptr_of(&mut a as *mut usize);
+        let ptr = va.as_non_null::<usize>().unwrap();
+        assert_eq!(ptr.as_ptr(), va.as_mut_ptr_of::<usize>());
+        assert_eq!(unsafe { *ptr.as_ptr() }, 0x1234);
+
+        assert!(VirtAddr::from_usize(0).as_non_null::<usize>().is_none());
+    }
+
+    #[test]
+    pub fn test_pfn() {
+        use crate::{Pfn, PAGE_SIZE_2M, PAGE_SIZE_4K};
+
+        // The top page of the address space.
+        let va = VirtAddr::from_usize(usize::MAX - 0xfff);
+        let pfn = va.pfn::<PAGE_SIZE_4K>();
+        assert_eq!(pfn, Pfn::new(usize::MAX >> 12));
+        assert_eq!(VirtAddr::from_pfn(pfn), va);
+        assert_eq!(pfn.start_addr::<VirtAddr>(), va);
+        assert_eq!(pfn.checked_add(usize::MAX), None);
+
+        let pa = PhysAddr::from_usize(0x8020_0000);
+        let pfn = pa.pfn::<PAGE_SIZE_4K>();
+        assert_eq!(pfn.as_usize(), 0x80200);
+        assert_eq!(PhysAddr::from_pfn(pfn), pa);
+        assert_eq!(PhysAddr::from_pfn(pfn.checked_add(1).unwrap()), pa + 0x1000);
+        assert_eq!(pa.pfn::<PAGE_SIZE_2M>(), Pfn::new(0x401));
+        assert_eq!(PhysAddr::from_pfn(Pfn::<PAGE_SIZE_2M>::new(0x401)), pa);
+        assert_eq!(format!("{:?}", pfn), "PFN:0x80200");
+
+        // Unaligned addresses map to the containing page.
+        assert_eq!((pa + 0xfff).pfn::<PAGE_SIZE_4K>(), Pfn::new(0x80200));
+        assert_eq!((pa + 0x1000).pfn::<PAGE_SIZE_4K>(), Pfn::new(0x80201));
+        assert_eq!(0x1234usize.pfn::<PAGE_SIZE_4K>(), Pfn::new(1));
+        assert_eq!(usize::from_pfn(Pfn::<PAGE_SIZE_4K>::new(0)), 0);
+
+        let narrow = NarrowAddr::from_raw(0xffff_f000);
+        assert_eq!(narrow.pfn::<PAGE_SIZE_4K>(), Pfn::new(0xfffff));
+        assert_eq!(
+            NarrowAddr::from_pfn(Pfn::<PAGE_SIZE_4K>::new(0xfffff)),
+            narrow
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow in `MemoryAddr::from_pfn`")]
+    pub fn test_from_pfn_overflow() {
+        let pfn = Pfn::<{ crate::PAGE_SIZE_4K }>::new((usize::MAX >> 12) + 1);
+        let _ = VirtAddr::from_pfn(pfn);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow in `MemoryAddr::from_pfn`")]
+    pub fn test_from_pfn_narrow_overflow() {
+        let _ = NarrowAddr::from_pfn(Pfn::<{ crate::PAGE_SIZE_4K }>::new(0x10_0000));
+    }
 }
@@ -0,0 +1,105 @@
+use crate::{PhysAddr, VirtAddr};
+
+/// Conversion between physical and virtual addresses with a fixed linear
+/// offset, i.e., `vaddr = paddr + offset`.
+///
+/// This is the common pattern of a higher-half kernel, where the physical
+/// memory is mapped at a fixed offset (`PHYS_VIRT_OFFSET`) in the kernel
+/// address space.
+///
+/// # Example
+///
+/// ```
+/// use memory_addr::{pa, va, LinearConv};
+///
+/// const CONV: LinearConv = LinearConv::new(0xc000_0000);
+///
+/// assert_eq!(CONV.phys_to_virt(pa!(0x10_0000)), Some(va!(0xc010_0000)));
+/// assert_eq!(CONV.virt_to_phys(va!(0xc010_0000)), Some(pa!(0x10_0000)));
+/// // Not in the linear mapping.
+/// assert_eq!(CONV.virt_to_phys(va!(0x1000)), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinearConv {
+    offset: usize,
+}
+
+impl LinearConv {
+    /// Creates a new converter with the given offset from physical addresses
+    /// to virtual addresses.
+    #[inline]
+    pub const fn new(offset: usize) -> Self {
+        Self { offset }
+    }
+
+    /// Returns the offset from physical addresses to virtual addresses.
+    #[inline]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Converts a physical address to the virtual address, i.e., `paddr +
+    /// offset`.
+    ///
+    /// Returns `None` if the result overflows.
+    #[inline]
+    pub const fn phys_to_virt(&self, paddr: PhysAddr) -> Option<VirtAddr> {
+        match paddr.as_usize().checked_add(self.offset) {
+            Some(vaddr) => Some(VirtAddr::from_usize(vaddr)),
+            None => None,
+        }
+    }
+
+    /// Converts a virtual address to the physical address, i.e., `vaddr -
+    /// offset`.
+    ///
+    /// Returns `None` if the result underflows, i.e., `vaddr` is below the
+    /// offset.
+    #[inline]
+    pub const fn virt_to_phys(&self, vaddr: VirtAddr) -> Option<PhysAddr> {
+        match vaddr.as_usize().checked_sub(self.offset) {
+            Some(paddr) => Some(PhysAddr::from_usize(paddr)),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LinearConv;
+    use crate::{pa, va};
+
+    #[test]
+    fn test_linear_conv() {
+        let conv = LinearConv::new(0xffff_0000);
+        assert_eq!(conv.offset(), 0xffff_0000);
+
+        assert_eq!(conv.phys_to_virt(pa!(0)), Some(va!(0xffff_0000)));
+        assert_eq!(conv.phys_to_virt(pa!(0x1234)), Some(va!(0xffff_1234)));
+        assert_eq!(
+            conv.phys_to_virt(pa!(usize::MAX - 0xffff_0000)),
+            Some(va!(usize::MAX))
+        );
+        assert_eq!(conv.phys_to_virt(pa!(usize::MAX - 0xfffe_ffff)), None);
+
+        assert_eq!(conv.virt_to_phys(va!(0xffff_1234)), Some(pa!(0x1234)));
+        assert_eq!(conv.virt_to_phys(va!(0xffff_0000)), Some(pa!(0)));
+        assert_eq!(conv.virt_to_phys(va!(0xfffe_ffff)), None);
+
+        // Round trip.
+        for paddr in [pa!(0), pa!(0x8020_0000), pa!(usize::MAX - 0xffff_0000)] {
+            let vaddr = conv.phys_to_virt(paddr).unwrap();
+            assert_eq!(conv.virt_to_phys(vaddr), Some(paddr));
+        }
+        let vaddr = va!(0xffff_abcd);
+        assert_eq!(
+            conv.phys_to_virt(conv.virt_to_phys(vaddr).unwrap()),
+            Some(vaddr)
+        );
+
+        // Zero offset is the identity mapping.
+        let identity = LinearConv::new(0);
+        assert_eq!(identity.phys_to_virt(pa!(0x1000)), Some(va!(0x1000)));
+        assert_eq!(identity.virt_to_phys(va!(0x1000)), Some(pa!(0x1000)));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::MemoryAddr;
+use core::fmt;
+
+use crate::{AddrRange, MemoryAddr};
 
 /// A page-by-page iterator.
 ///
@@ -19,6 +21,7 @@ use crate::MemoryAddr;
 ///
 /// assert!(PageIter::<0x1000, usize>::new(0x1000, 0x3001).is_none());
 /// ```
+#[derive(Clone)]
 pub struct PageIter<const PAGE_SIZE: usize, A>
 where
     A: MemoryAddr,
@@ -45,6 +48,57 @@ where
             Some(Self { start, end })
         }
     }
+
+    /// Returns the range of pages that have not been yielded yet.
+    pub fn remaining_range(&self) -> AddrRange<A> {
+        AddrRange {
+            start: self.start.min(self.end),
+            end: self.end,
+        }
+    }
+
+    /// Checks whether `addr` is one of the page addresses that have not been
+    /// yielded yet, without iterating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memory_addr::PageIter;
+    ///
+    /// let iter = PageIter::<0x1000, usize>::new(0x1000, 0x3000).unwrap();
+    /// assert!(iter.contains(0x2000));
+    /// assert!(!iter.contains(0x2800));
+    /// assert!(!iter.contains(0x3000));
+    /// ```
+    pub fn contains(&self, addr: A) -> bool {
+        addr.is_aligned(PAGE_SIZE) && self.start <= addr && addr < self.end
+    }
+
+    /// Returns an iterator that advances `n` pages per step, starting from the
+    /// current position and stopping before `end`.
+    ///
+    /// Returns `None` if `n` is 0, or `n * PAGE_SIZE` overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use memory_addr::PageIter;
+    ///
+    /// let iter = PageIter::<0x1000, usize>::new(0, 0x5000).unwrap();
+    /// let pages = iter.step_by_pages(2).unwrap().collect::<Vec<_>>();
+    /// assert_eq!(pages, [0, 0x2000, 0x4000]);
+    /// ```
+    pub fn step_by_pages(self, n: usize) -> Option<PageStepIter<PAGE_SIZE, A>> {
+        if n == 0 {
+            return None;
+        }
+        let step = n.checked_mul(PAGE_SIZE)?;
+        Some(PageStepIter {
+            start: self.start,
+            end: self.end,
+            step,
+        })
+    }
 }
 
 impl<A, const PAGE_SIZE: usize> Iterator for PageIter<PAGE_SIZE, A>
@@ -63,3 +117,216 @@ where
         }
     }
 }
+
+impl<A, const PAGE_SIZE: usize> fmt::Debug for PageIter<PAGE_SIZE, A>
+where
+    A: MemoryAddr + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PageIter")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("page_size", &format_args!("{:#x}", PAGE_SIZE))
+            .finish()
+    }
+}
+
+/// An iterator that advances a fixed number of pages per step.
+///
+/// It is created by [`PageIter::step_by_pages`].
+#[derive(Clone, Debug)]
+pub struct PageStepIter<const PAGE_SIZE: usize, A>
+where
+    A: MemoryAddr,
+{
+    start: A,
+    end: A,
+    step: usize,
+}
+
+impl<A, const PAGE_SIZE: usize> Iterator for PageStepIter<PAGE_SIZE, A>
+where
+    A: MemoryAddr,
+{
+    type Item = A;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start < self.end {
+            let ret = self.start;
+            // Clamp to `end` so that a large step never overflows.
+            self.start = self
+                .start
+                .checked_add(self.step)
+                .map_or(self.end, |next| next.min(self.end));
+            Some(ret)
+        } else {
+            None
+        }
+    }
+}
+
+/// A page-by-page iterator that yields the address range of each page.
+///
+/// Unlike [`PageIter`], which only yields the start address of each page, this
+/// iterator yields an [`AddrRange`] covering the whole page.
+///
+/// The page size is specified by the generic parameter `PAGE_SIZE`, which must
+/// be a power of 2.
+///
+/// The address type is specified by the type parameter `A`.
+///
+/// # Examples
+///
+/// ```
+/// use memory_addr::{addr_range, PageRangeIter};
+///
+/// let mut iter = PageRangeIter::<0x1000, usize>::new(0x1000, 0x3000).unwrap();
+/// assert_eq!(iter.next(), Some(addr_range!(0x1000usize..0x2000)));
+/// assert_eq!(iter.next(), Some(addr_range!(0x2000usize..0x3000)));
+/// assert_eq!(iter.next(), None);
+///
+/// assert!(PageRangeIter::<0x1000, usize>::new(0x1000, 0x3001).is_none());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PageRangeIter<const PAGE_SIZE: usize, A>
+where
+    A: MemoryAddr,
+{
+    inner: PageIter<PAGE_SIZE, A>,
+}
+
+impl<A, const PAGE_SIZE: usize> PageRangeIter<PAGE_SIZE, A>
+where
+    A: MemoryAddr,
+{
+    /// Creates a new [`PageRangeIter`].
+    ///
+    /// Returns `None` if `PAGE_SIZE` is not a power of 2, or `start` or `end`
+    /// is not page-aligned.
+    pub fn new(start: A, end: A) -> Option<Self> {
+        PageIter::new(start, end).map(|inner| Self { inner })
+    }
+}
+
+impl<A, const PAGE_SIZE: usize> Iterator for PageRangeIter<PAGE_SIZE, A>
+where
+    A: MemoryAddr,
+{
+    type Item = AddrRange<A>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            // Safety: `start` is page-aligned and less than `end`, which is
+            // also page-aligned, so `start + PAGE_SIZE` never overflows.
+            .map(|start| unsafe { AddrRange::from_start_size_unchecked(start, PAGE_SIZE) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_new_unaligned() {
+        assert!(PageIter::<0x1000, usize>::new(0x1000, 0x3000).is_some());
+        assert!(PageIter::<0x1000, usize>::new(0x1000, 0x3001).is_none());
+        assert!(PageIter::<0x1000, usize>::new(0x1001, 0x3000).is_none());
+        assert!(PageIter::<0x1001, usize>::new(0x1001, 0x2002).is_none());
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut iter = PageIter::<0x1000, usize>::new(0x1000, 0x4000).unwrap();
+        assert!(iter.contains(0x1000));
+        assert!(iter.contains(0x3000));
+        assert!(!iter.contains(0));
+        assert!(!iter.contains(0x4000));
+        assert!(!iter.contains(0x1001));
+        assert!(!iter.contains(0x2fff));
+
+        // Yielded pages are not contained any more.
+        iter.next();
+        assert!(!iter.contains(0x1000));
+        assert!(iter.contains(0x2000));
+        assert_eq!(
+            iter.clone().any(|addr| addr == 0x3000),
+            iter.contains(0x3000)
+        );
+    }
+
+    #[test]
+    fn test_remaining_range() {
+        let mut iter = PageIter::<0x1000, usize>::new(0x1000, 0x3000).unwrap();
+        assert_eq!(iter.remaining_range(), AddrRange::new(0x1000, 0x3000));
+        iter.next();
+        assert_eq!(iter.remaining_range(), AddrRange::new(0x2000, 0x3000));
+        iter.next();
+        assert!(iter.remaining_range().is_empty());
+        iter.next();
+        assert!(iter.remaining_range().is_empty());
+    }
+
+    #[test]
+    fn test_step_by_pages() {
+        let iter = PageIter::<0x1000, usize>::new(0, 0x8000).unwrap();
+        let pages = iter.step_by_pages(2).unwrap().collect::<Vec<_>>();
+        assert_eq!(pages, [0, 0x2000, 0x4000, 0x6000]);
+
+        let iter = PageIter::<0x1000, usize>::new(0, 0x8000).unwrap();
+        assert!(iter.step_by_pages(0).is_none());
+
+        let iter = PageIter::<0x1000, usize>::new(0, 0x8000).unwrap();
+        let pages = iter
+            .step_by_pages(usize::MAX >> 12)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(pages, [0]);
+    }
+
+    #[test]
+    fn test_clone_debug() {
+        let mut iter = PageIter::<0x1000, usize>::new(0x1000, 0x4000).unwrap();
+        iter.next();
+        let mut cloned = iter.clone();
+        assert_eq!(cloned.clone().count(), 2);
+        assert_eq!(cloned.next(), Some(0x2000));
+        assert_eq!(cloned.next(), Some(0x3000));
+        assert_eq!(cloned.next(), None);
+        assert_eq!(iter.next(), Some(0x2000));
+
+        assert_eq!(
+            format!("{:?}", iter),
+            "PageIter { start: 12288, end: 16384, page_size: 0x1000 }"
+        );
+    }
+
+    #[test]
+    fn test_clone_debug_step_and_range() {
+        let mut iter = PageIter::<0x1000, usize>::new(0x1000, 0x7000)
+            .unwrap()
+            .step_by_pages(2)
+            .unwrap();
+        iter.next();
+        let mut cloned = iter.clone();
+        assert_eq!(cloned.next(), Some(0x3000));
+        assert_eq!(cloned.next(), Some(0x5000));
+        assert_eq!(cloned.next(), None);
+        assert_eq!(iter.next(), Some(0x3000));
+        assert_eq!(
+            format!("{:?}", iter),
+            "PageStepIter { start: 20480, end: 28672, step: 8192 }"
+        );
+
+        let mut iter = PageRangeIter::<0x1000, usize>::new(0x1000, 0x4000).unwrap();
+        iter.next();
+        let mut cloned = iter.clone();
+        assert_eq!(cloned.next(), Some(AddrRange::new(0x2000, 0x3000)));
+        assert_eq!(cloned.count(), 1);
+        assert_eq!(iter.next(), Some(AddrRange::new(0x2000, 0x3000)));
+        assert_eq!(
+            format!("{:?}", iter),
+            "PageRangeIter { inner: PageIter { start: 12288, end: 16384, page_size: 0x1000 } }"
+        );
+    }
+}
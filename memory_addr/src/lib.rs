@@ -1,20 +1,61 @@
 #![cfg_attr(not(test), no_std)]
+#![cfg_attr(
+    feature = "step_trait",
+    feature(allow_internal_unstable),
+    allow(internal_features)
+)]
+#![cfg_attr(all(test, feature = "step_trait"), feature(step_trait))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 mod addr;
+mod conv;
 mod iter;
+mod pfn;
 mod range;
 
 pub use self::addr::{MemoryAddr, PhysAddr, VirtAddr};
-pub use self::iter::PageIter;
-pub use self::range::{AddrRange, PhysAddrRange, VirtAddrRange};
+pub use self::conv::LinearConv;
+pub use self::iter::{PageIter, PageRangeIter, PageStepIter};
+pub use self::pfn::Pfn;
+pub use self::range::{
+    overlaps_any, page_aligned_range_4k, AddrRange, PhysAddrRange, VirtAddrRange,
+};
+#[cfg(all(feature = "arbitrary", not(target_os = "none")))]
+#[doc(hidden)]
+pub use arbitrary as __arbitrary;
+#[cfg(feature = "bytemuck")]
+#[doc(hidden)]
+pub use bytemuck as __bytemuck;
 
 /// The size of a 4K page (4096 bytes).
 pub const PAGE_SIZE_4K: usize = 0x1000;
+/// The size of a 2M page (2 MiB).
+pub const PAGE_SIZE_2M: usize = 0x20_0000;
+/// The size of a 1G page (1 GiB).
+pub const PAGE_SIZE_1G: usize = 0x4000_0000;
+
+/// The mask of the offset within a 4K page, i.e., `PAGE_SIZE_4K - 1`.
+pub const PAGE_MASK_4K: usize = page_mask(PAGE_SIZE_4K);
+/// The mask of the offset within a 2M page, i.e., `PAGE_SIZE_2M - 1`.
+pub const PAGE_MASK_2M: usize = page_mask(PAGE_SIZE_2M);
+/// The mask of the offset within a 1G page, i.e., `PAGE_SIZE_1G - 1`.
+pub const PAGE_MASK_1G: usize = page_mask(PAGE_SIZE_1G);
 
 /// A [`PageIter`] for 4K pages.
 pub type PageIter4K<A> = PageIter<PAGE_SIZE_4K, A>;
 
+/// Returns the mask of the offset within a page of the given size, i.e.,
+/// `page_size - 1`.
+///
+/// The page size must be a power of two.
+#[inline]
+pub const fn page_mask(page_size: usize) -> usize {
+    page_size - 1
+}
+
 /// Align address downwards.
 ///
 /// Returns the greatest `x` with alignment `align` so that `x <= addr`.
@@ -93,4 +134,17 @@ mod tests {
         assert!(is_aligned_4k(0x12345000));
         assert!(!is_aligned_4k(0x12345678));
     }
+
+    #[test]
+    fn test_page_mask() {
+        const OFFSET: usize = 0x12345678 & PAGE_MASK_4K;
+        const BASE_2M: usize = 0x12345678 & !PAGE_MASK_2M;
+        assert_eq!(OFFSET, 0x678);
+        assert_eq!(BASE_2M, 0x12200000);
+        assert_eq!(0x12345678 & PAGE_MASK_1G, 0x12345678);
+        assert_eq!(page_mask(0x10), 0xf);
+        assert_eq!(PAGE_MASK_4K, 0xfff);
+        assert_eq!(PAGE_MASK_2M, 0x1f_ffff);
+        assert_eq!(PAGE_MASK_1G, 0x3fff_ffff);
+    }
 }
@@ -0,0 +1,64 @@
+use core::fmt;
+
+use crate::MemoryAddr;
+
+/// A page frame number (PFN), i.e., the index of a page of `PAGE_SIZE` bytes
+/// (a power of two) in the address space.
+///
+/// It is a distinct type from the address types, so that page numbers and
+/// byte addresses can not be mixed up by accident. Use
+/// [`MemoryAddr::pfn`] and [`MemoryAddr::from_pfn`] to convert between them.
+///
+/// # Example
+///
+/// ```
+/// use memory_addr::{pa, MemoryAddr, Pfn, PhysAddr, PAGE_SIZE_4K};
+///
+/// let pfn: Pfn<PAGE_SIZE_4K> = pa!(0x1234_5678).pfn();
+/// assert_eq!(pfn, Pfn::new(0x12345));
+/// assert_eq!(pfn.as_usize(), 0x12345);
+/// assert_eq!(PhysAddr::from_pfn(pfn), pa!(0x1234_5000));
+/// assert_eq!(pfn.start_addr::<PhysAddr>(), pa!(0x1234_5000));
+/// ```
+#[repr(transparent)]
+#[derive(Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Pfn<const PAGE_SIZE: usize>(usize);
+
+impl<const PAGE_SIZE: usize> Pfn<PAGE_SIZE> {
+    /// Creates a page frame number from its raw value.
+    #[inline]
+    pub const fn new(pfn: usize) -> Self {
+        Self(pfn)
+    }
+
+    /// Returns the raw value of the page frame number.
+    #[inline]
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+
+    /// Returns the start address of the page.
+    ///
+    /// Equivalent to [`A::from_pfn(self)`](MemoryAddr::from_pfn), and panics
+    /// in the same way.
+    #[inline]
+    pub fn start_addr<A: MemoryAddr>(self) -> A {
+        A::from_pfn(self)
+    }
+
+    /// Adds a number of pages to the page frame number, returning `None` on
+    /// overflow.
+    #[inline]
+    pub const fn checked_add(self, count: usize) -> Option<Self> {
+        match self.0.checked_add(count) {
+            Some(pfn) => Some(Self(pfn)),
+            None => None,
+        }
+    }
+}
+
+impl<const PAGE_SIZE: usize> fmt::Debug for Pfn<PAGE_SIZE> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PFN:{:#x}", self.0)
+    }
+}
@@ -1,6 +1,9 @@
-use core::{fmt, ops::Range};
+use core::{
+    fmt,
+    ops::{Range, RangeInclusive},
+};
 
-use crate::{MemoryAddr, PhysAddr, VirtAddr};
+use crate::{MemoryAddr, PageRangeIter, PhysAddr, VirtAddr};
 
 /// A range of a given memory address type `A`.
 ///
@@ -8,7 +11,15 @@ use crate::{MemoryAddr, PhysAddr, VirtAddr};
 /// considered **empty** iff `start == end`, and **invalid** iff `start > end`.
 /// An invalid range should not be created and cannot be obtained without unsafe
 /// operations, calling methods on an invalid range will cause unexpected
-/// consequences.
+/// consequences. In debug builds, methods like [`AddrRange::size`] and
+/// [`AddrRange::overlaps`] panic on an invalid range to catch such misuse.
+///
+/// Since the end is exclusive and must be representable by `A`, a range can
+/// never cover the last address of the address space (e.g., `usize::MAX`).
+/// Consequently, the top page (e.g., `[usize::MAX - 0xfff, usize::MAX]` for 4K
+/// pages) cannot be covered by any page-aligned range. The checked
+/// constructors like [`AddrRange::try_from_start_size`] return `None` for
+/// such ranges instead of overflowing.
 ///
 /// # Example
 ///
@@ -19,7 +30,10 @@ use crate::{MemoryAddr, PhysAddr, VirtAddr};
 /// assert_eq!(range.start, 0x1000);
 /// assert_eq!(range.end, 0x2000);
 /// ```
-#[derive(Clone, Copy, PartialEq, Eq)]
+///
+/// Ranges are ordered by their start addresses first, and then by their end
+/// addresses.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AddrRange<A: MemoryAddr> {
     /// The lower bound of the range (inclusive).
     pub start: A,
@@ -166,6 +180,29 @@ where
         start.checked_add(size).map(|end| Self { start, end })
     }
 
+    /// Creates a new address range with the start address aligned up to
+    /// `align` and the size rounded up to a multiple of `align`, which must be
+    /// a power of two.
+    ///
+    /// Returns `None` if aligning `start` or `size`, or computing the end
+    /// address overflows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, va_range, VirtAddrRange};
+    ///
+    /// let range = VirtAddrRange::from_aligned(va!(0x1234), 0x1800, 0x1000);
+    /// assert_eq!(range, Some(va_range!(0x2000..0x4000)));
+    /// assert!(VirtAddrRange::from_aligned(va!(usize::MAX - 0xfff), 0x1000, 0x1000).is_none());
+    /// ```
+    #[inline]
+    pub fn from_aligned(start: A, size: usize, align: usize) -> Option<Self> {
+        let start = start.checked_align_up(align)?;
+        let size = size.checked_align_up(align)?;
+        Self::try_from_start_size(start, size)
+    }
+
     /// Creates a new address range from the start address and the size without
     /// checking the validity.
     ///
@@ -192,6 +229,55 @@ where
         }
     }
 
+    /// Creates a new address range from the start address and the last
+    /// address (inclusive), e.g., from a firmware table describing a register
+    /// block.
+    ///
+    /// Returns `None` if `start > last`, or `last` is the last address of the
+    /// address space (e.g., `usize::MAX`), since the exclusive end `last + 1`
+    /// is not representable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{pa, pa_range, PhysAddrRange};
+    ///
+    /// let range = PhysAddrRange::from_inclusive(pa!(0x1000), pa!(0x1fff));
+    /// assert_eq!(range, Some(pa_range!(0x1000..0x2000)));
+    /// assert!(PhysAddrRange::from_inclusive(pa!(0x2000), pa!(0x1fff)).is_none());
+    /// assert!(PhysAddrRange::from_inclusive(pa!(usize::MAX - 0xfff), pa!(usize::MAX)).is_none());
+    /// ```
+    #[inline]
+    pub fn from_inclusive(start: A, last: A) -> Option<Self> {
+        if start > last {
+            return None;
+        }
+        last.checked_add(1).map(|end| Self { start, end })
+    }
+
+    /// Converts the range into a [`RangeInclusive`] from the start address to
+    /// the last address in the range.
+    ///
+    /// Returns `None` if the range is empty, which can not be represented with
+    /// an inclusive end.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{pa, pa_range};
+    ///
+    /// assert_eq!(pa_range!(0x1000..0x2000).to_inclusive(), Some(pa!(0x1000)..=pa!(0x1fff)));
+    /// assert_eq!(pa_range!(0x1000..0x1000).to_inclusive(), None);
+    /// ```
+    #[inline]
+    pub fn to_inclusive(self) -> Option<RangeInclusive<A>> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.start..=self.end.sub(1))
+        }
+    }
+
     /// Returns `true` if the range is empty.
     ///
     /// It's also guaranteed that `false` will be returned if the range is
@@ -210,6 +296,15 @@ where
         self.start >= self.end
     }
 
+    /// Panics in debug builds if the range is invalid (i.e., `start > end`),
+    /// which can only be created by unsafe constructors like
+    /// [`AddrRange::new_unchecked`].
+    #[inline]
+    #[track_caller]
+    fn debug_assert_valid(self) {
+        debug_assert!(self.start <= self.end, "invalid `AddrRange`: start > end");
+    }
+
     /// Returns the size of the range.
     ///
     /// # Example
@@ -222,6 +317,7 @@ where
     /// ```
     #[inline]
     pub fn size(self) -> usize {
+        self.debug_assert_valid();
         self.end.wrapping_sub_addr(self.start)
     }
 
@@ -240,6 +336,7 @@ where
     /// ```
     #[inline]
     pub fn contains(self, addr: A) -> bool {
+        self.debug_assert_valid();
         self.start <= addr && addr < self.end
     }
 
@@ -260,9 +357,42 @@ where
     /// ```
     #[inline]
     pub fn contains_range(self, other: Self) -> bool {
+        self.debug_assert_valid();
+        other.debug_assert_valid();
         self.start <= other.start && other.end <= self.end
     }
 
+    /// Checks if the range contains the given range, where `other` is treated
+    /// as an **inclusive** range `[other.start, other.end]`.
+    ///
+    /// That is, `other.end` is the address of the last byte of `other` (as for
+    /// device ranges given by their first and last addresses), rather than the
+    /// address after it as elsewhere in this crate. `self` is still exclusive
+    /// at its end, so this returns `true` if and only if `self.start <=
+    /// other.start` and `other.end < self.end`.
+    ///
+    /// Compared to [`contains_range`](Self::contains_range), `other` covers
+    /// one more byte at its end, so it can not touch the end of `self`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{addr_range, AddrRange};
+    ///
+    /// let range = AddrRange::new(0x1000usize, 0x2000);
+    /// // [0x1000, 0x1fff] is within [0x1000, 0x2000).
+    /// assert!(range.contains_range_inclusive(addr_range!(0x1000usize..0x1fff)));
+    /// // [0x1000, 0x2000] is not, since 0x2000 is outside.
+    /// assert!(!range.contains_range_inclusive(addr_range!(0x1000usize..0x2000)));
+    /// assert!(range.contains_range(addr_range!(0x1000usize..0x2000)));
+    /// ```
+    #[inline]
+    pub fn contains_range_inclusive(self, other: Self) -> bool {
+        self.debug_assert_valid();
+        other.debug_assert_valid();
+        self.start <= other.start && other.end < self.end
+    }
+
     /// Checks if the range is contained in the given address range.
     ///
     /// # Example
@@ -298,8 +428,313 @@ where
     /// ```
     #[inline]
     pub fn overlaps(self, other: Self) -> bool {
+        self.debug_assert_valid();
+        other.debug_assert_valid();
         self.start < other.end && other.start < self.end
     }
+
+    /// Returns the intersection of the range and the given address range, or
+    /// `None` if they do not overlap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{addr_range, AddrRange};
+    ///
+    /// let range = AddrRange::new(0x1000usize, 0x3000usize);
+    /// assert_eq!(
+    ///     range.intersect(addr_range!(0x2000usize..0x4000)),
+    ///     Some(addr_range!(0x2000usize..0x3000))
+    /// );
+    /// assert_eq!(range.intersect(addr_range!(0x3000usize..0x4000)), None);
+    /// ```
+    #[inline]
+    pub fn intersect(self, other: Self) -> Option<Self> {
+        if self.overlaps(other) {
+            Some(Self {
+                start: self.start.max(other.start),
+                end: self.end.min(other.end),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Checks if both the start and end addresses of the range are aligned to
+    /// `align`, which must be a power of two.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va_range, VirtAddrRange};
+    ///
+    /// let range: VirtAddrRange = va_range!(0x2000..0x6000);
+    /// assert!(range.is_aligned(0x2000));
+    /// assert!(!range.is_aligned(0x4000));
+    /// assert!(!va_range!(0x2000..0x6100).is_aligned(0x1000));
+    /// ```
+    #[inline]
+    pub fn is_aligned(self, align: usize) -> bool {
+        self.start.is_aligned(align) && self.end.is_aligned(align)
+    }
+
+    /// Checks if both the start and end addresses of the range are 4K-aligned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::va_range;
+    ///
+    /// assert!(va_range!(0x1000..0x3000).is_aligned_4k());
+    /// assert!(!va_range!(0x1000..0x3008).is_aligned_4k());
+    /// assert!(!va_range!(0x1800..0x3000).is_aligned_4k());
+    /// ```
+    #[inline]
+    pub fn is_aligned_4k(self) -> bool {
+        self.is_aligned(crate::PAGE_SIZE_4K)
+    }
+
+    /// Removes all the `holes` from the range, and returns the remaining
+    /// disjoint pieces in ascending order.
+    ///
+    /// The holes can be in any order, and may overlap with or be adjacent to
+    /// each other. Parts of the holes outside the range are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::va_range;
+    ///
+    /// let range = va_range!(0x1000..0x8000);
+    /// let holes = [va_range!(0x5000..0x6000), va_range!(0x2000..0x3000)];
+    /// assert_eq!(
+    ///     range.subtract_all(&holes),
+    ///     [
+    ///         va_range!(0x1000..0x2000),
+    ///         va_range!(0x3000..0x5000),
+    ///         va_range!(0x6000..0x8000),
+    ///     ]
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn subtract_all(self, holes: &[Self]) -> alloc::vec::Vec<Self> {
+        let mut holes = holes.to_vec();
+        holes.sort_unstable_by_key(|hole| hole.start);
+
+        let mut pieces = alloc::vec::Vec::new();
+        let mut cursor = self.start;
+        for hole in holes {
+            if hole.is_empty() || hole.end <= cursor {
+                continue;
+            }
+            if hole.start >= self.end {
+                break;
+            }
+            if hole.start > cursor {
+                pieces.push(Self {
+                    start: cursor,
+                    end: hole.start,
+                });
+            }
+            cursor = hole.end;
+        }
+        if cursor < self.end {
+            pieces.push(Self {
+                start: cursor,
+                end: self.end,
+            });
+        }
+        pieces
+    }
+
+    /// Returns the smallest range aligned to `align` that covers this range,
+    /// i.e., the start address is aligned down and the end address is aligned
+    /// up. The alignment must be a power of two.
+    ///
+    /// Returns `None` if aligning the end address up overflows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, va_range, VirtAddrRange};
+    ///
+    /// let range = va_range!(0x1234..0x5678);
+    /// assert_eq!(
+    ///     range.checked_align_outward(0x1000),
+    ///     Some(va_range!(0x1000..0x6000))
+    /// );
+    ///
+    /// let top = VirtAddrRange::new(va!(0x1000), va!(usize::MAX - 1));
+    /// assert_eq!(top.checked_align_outward(0x1000), None);
+    /// ```
+    #[inline]
+    pub fn checked_align_outward(self, align: usize) -> Option<Self> {
+        Some(Self {
+            start: self.start.align_down(align),
+            end: self.end.checked_align_up(align)?,
+        })
+    }
+
+    /// Returns an iterator over the sub-ranges of this range, split at every
+    /// multiple of `align`, which must be a power of two.
+    ///
+    /// The first and the last sub-ranges may be smaller than `align` if the
+    /// range is not aligned, while all the sub-ranges in between are exactly
+    /// `align` bytes. Nothing is yielded for an empty range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::va_range;
+    ///
+    /// let pieces = va_range!(0x1800..0x4200).split_by_alignment(0x1000);
+    /// assert_eq!(
+    ///     pieces.collect::<Vec<_>>(),
+    ///     [
+    ///         va_range!(0x1800..0x2000),
+    ///         va_range!(0x2000..0x3000),
+    ///         va_range!(0x3000..0x4000),
+    ///         va_range!(0x4000..0x4200),
+    ///     ]
+    /// );
+    /// ```
+    #[inline]
+    pub fn split_by_alignment(self, align: usize) -> impl Iterator<Item = Self> {
+        let mut cursor = self.start;
+        core::iter::from_fn(move || {
+            if cursor >= self.end {
+                return None;
+            }
+            let next = cursor
+                .align_down(align)
+                .checked_add(align)
+                .map_or(self.end, |boundary| boundary.min(self.end));
+            let piece = Self {
+                start: cursor,
+                end: next,
+            };
+            cursor = next;
+            Some(piece)
+        })
+    }
+
+    /// Returns an iterator over the segments of this range for scatter-gather
+    /// DMA, where each segment is at most `max_seg` bytes and does not cross a
+    /// multiple of `boundary`, which must be a power of two.
+    ///
+    /// The segments are non-empty, in ascending order and cover the whole
+    /// range. Nothing is yielded for an empty range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_seg` is 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::va_range;
+    ///
+    /// let segments = va_range!(0x1800..0x4000).dma_segments(0x1000, 0x2000);
+    /// assert_eq!(
+    ///     segments.collect::<Vec<_>>(),
+    ///     [
+    ///         va_range!(0x1800..0x2000),
+    ///         va_range!(0x2000..0x3000),
+    ///         va_range!(0x3000..0x4000),
+    ///     ]
+    /// );
+    /// ```
+    pub fn dma_segments(self, max_seg: usize, boundary: usize) -> impl Iterator<Item = Self> {
+        assert!(max_seg > 0, "`max_seg` must not be 0");
+        self.split_by_alignment(boundary).flat_map(move |piece| {
+            let mut cursor = piece.start;
+            core::iter::from_fn(move || {
+                if cursor >= piece.end {
+                    return None;
+                }
+                let next = cursor
+                    .checked_add(max_seg)
+                    .map_or(piece.end, |next| next.min(piece.end));
+                let segment = Self {
+                    start: cursor,
+                    end: next,
+                };
+                cursor = next;
+                Some(segment)
+            })
+        })
+    }
+
+    /// Applies the given function to both endpoints of the range, and returns
+    /// the resulting range.
+    ///
+    /// This is useful for translating a range into a different address space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the resulting range is invalid, i.e., `f(start) > f(end)`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::AddrRange;
+    ///
+    /// let range = AddrRange::new(0x1000usize, 0x2000);
+    /// let shifted = range.map_addr(|addr| addr + 0x8000);
+    /// assert_eq!(shifted, AddrRange::new(0x9000, 0xa000));
+    /// ```
+    #[inline]
+    pub fn map_addr(self, f: impl Fn(A) -> A) -> Self {
+        Self::new(f(self.start), f(self.end))
+    }
+
+    /// Returns a [`Display`](fmt::Display) wrapper that prints the range in
+    /// the [`Debug`](fmt::Debug) format, followed by a human-readable size,
+    /// e.g., `VA:0x1000..VA:0x3000 (8 KiB)`.
+    ///
+    /// The size is shown in the largest binary unit (KiB, MiB, GiB or TiB)
+    /// that divides it exactly, or in bytes otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::va_range;
+    ///
+    /// let range = va_range!(0x1000..0x3000);
+    /// assert_eq!(
+    ///     range.display_with_size().to_string(),
+    ///     "VA:0x1000..VA:0x3000 (8 KiB)"
+    /// );
+    /// ```
+    pub fn display_with_size(self) -> impl fmt::Display
+    where
+        A: fmt::Debug,
+    {
+        DisplayWithSize(self)
+    }
+
+    /// Returns an iterator over the page-sized ranges covering this range.
+    ///
+    /// Returns `None` if `PAGE_SIZE` is not a power of 2, or `start` or `end`
+    /// is not page-aligned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{addr_range, AddrRange};
+    ///
+    /// let range: AddrRange<usize> = addr_range!(0x1000usize..0x3000);
+    /// let mut iter = range.page_ranges::<0x1000>().unwrap();
+    /// assert_eq!(iter.next(), Some(addr_range!(0x1000usize..0x2000)));
+    /// assert_eq!(iter.next(), Some(addr_range!(0x2000usize..0x3000)));
+    /// assert_eq!(iter.next(), None);
+    ///
+    /// assert!(AddrRange::new(0x1000usize, 0x3001).page_ranges::<0x1000>().is_none());
+    /// ```
+    #[inline]
+    pub fn page_ranges<const PAGE_SIZE: usize>(self) -> Option<PageRangeIter<PAGE_SIZE, A>> {
+        PageRangeIter::new(self.start, self.end)
+    }
 }
 
 /// Conversion from [`Range`] to [`AddrRange`], provided that the type of the
@@ -362,11 +797,163 @@ where
     }
 }
 
+/// The [`Display`](fmt::Display) wrapper returned by
+/// [`AddrRange::display_with_size`].
+struct DisplayWithSize<A: MemoryAddr>(AddrRange<A>);
+
+impl<A> fmt::Display for DisplayWithSize<A>
+where
+    A: MemoryAddr + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const UNITS: [(&str, u32); 4] = [("TiB", 40), ("GiB", 30), ("MiB", 20), ("KiB", 10)];
+        // Use `u64` so that the shifts never overflow on 32-bit targets.
+        let size = self.0.size() as u64;
+        write!(f, "{:?} (", self.0)?;
+        for (unit, shift) in UNITS {
+            let unit_size = 1u64 << shift;
+            if size >= unit_size && size.is_multiple_of(unit_size) {
+                return write!(f, "{} {})", size >> shift, unit);
+            }
+        }
+        write!(f, "{} B)", size)
+    }
+}
+
+/// Implementations of `arbitrary::Arbitrary` for [`AddrRange`].
+///
+/// Two arbitrary endpoints are generated and sorted, so the range is always
+/// valid (`start <= end`).
+#[cfg(all(feature = "arbitrary", not(target_os = "none")))]
+impl<'a, A> arbitrary::Arbitrary<'a> for AddrRange<A>
+where
+    A: MemoryAddr + arbitrary::Arbitrary<'a>,
+{
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let a = A::arbitrary(u)?;
+        let b = A::arbitrary(u)?;
+        Ok(Self {
+            start: a.min(b),
+            end: a.max(b),
+        })
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        arbitrary::size_hint::and(A::size_hint(depth), A::size_hint(depth))
+    }
+}
+
+/// Returns the smallest 4K-aligned address range that covers the memory region
+/// starting at `start` with the given `size`.
+///
+/// That is, `[align_down_4k(start), align_up_4k(start + size))`.
+///
+/// # Panics
+///
+/// Panics if `start + size` or the aligned end address overflows.
+///
+/// # Example
+///
+/// ```
+/// use memory_addr::{page_aligned_range_4k, va, va_range};
+///
+/// let range = page_aligned_range_4k(va!(0x1234), 0x2000);
+/// assert_eq!(range, va_range!(0x1000..0x4000));
+/// ```
+#[inline]
+pub fn page_aligned_range_4k<A: MemoryAddr>(start: A, size: usize) -> AddrRange<A> {
+    let end = start.add(size);
+    let aligned_end = end
+        .into()
+        .checked_next_multiple_of(crate::PAGE_SIZE_4K)
+        .expect("overflow in `page_aligned_range_4k`");
+    AddrRange {
+        start: start.align_down_4k(),
+        end: A::from(aligned_end),
+    }
+}
+
+/// Checks if `range` overlaps with any of the address ranges in `others`.
+///
+/// It stops at the first overlapping range. See [`AddrRange::overlaps`] for
+/// the definition of overlapping.
+///
+/// # Example
+///
+/// ```
+/// use memory_addr::{overlaps_any, va_range};
+///
+/// let reserved = [va_range!(0x1000..0x2000), va_range!(0x8000..0x9000)];
+/// assert!(overlaps_any(va_range!(0x1800..0x3000), &reserved));
+/// assert!(!overlaps_any(va_range!(0x2000..0x8000), &reserved));
+/// ```
+#[inline]
+pub fn overlaps_any<A: MemoryAddr>(range: AddrRange<A>, others: &[AddrRange<A>]) -> bool {
+    others.iter().any(|&other| range.overlaps(other))
+}
+
 /// A range of virtual addresses [`VirtAddr`].
 pub type VirtAddrRange = AddrRange<VirtAddr>;
 /// A range of physical addresses [`PhysAddr`].
 pub type PhysAddrRange = AddrRange<PhysAddr>;
 
+macro_rules! impl_const_range {
+    ($addr:ident, $range:ident, $macro:literal) => {
+        impl $range {
+            #[doc = concat!("A `const` version of [`AddrRange::from_start_size`] for [`", stringify!($addr), "`].")]
+            ///
+            /// It can be used to define address ranges at compile time, where an
+            /// overflow is reported as a compile error.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `size` is too large and causes overflow during evaluating
+            /// the end address.
+            ///
+            /// # Example
+            ///
+            /// ```
+            #[doc = concat!("use memory_addr::{", stringify!($addr), ", ", stringify!($range), ", ", $macro, "};")]
+            ///
+            #[doc = concat!("const RANGE: ", stringify!($range), " = ", stringify!($range), "::from_start_size_const(")]
+            #[doc = concat!("    ", stringify!($addr), "::from_usize(0x1000),")]
+            ///     0x2000,
+            /// );
+            #[doc = concat!("assert_eq!(RANGE, ", $macro, "!(0x1000..0x3000));")]
+            /// ```
+            #[inline]
+            pub const fn from_start_size_const(start: $addr, size: usize) -> Self {
+                match start.checked_add_usize(size) {
+                    Some(end) => Self { start, end },
+                    None => panic!("size too large for `AddrRange`"),
+                }
+            }
+
+            #[doc = concat!("A `const` version of [`AddrRange::size`] for [`", stringify!($addr), "`].")]
+            ///
+            /// # Example
+            ///
+            /// ```
+            #[doc = concat!("use memory_addr::{", stringify!($addr), ", ", stringify!($range), "};")]
+            ///
+            #[doc = concat!("const RANGE: ", stringify!($range), " = ", stringify!($range), "::from_start_size_const(")]
+            #[doc = concat!("    ", stringify!($addr), "::from_usize(0x1000),")]
+            ///     0x2000,
+            /// );
+            /// const SIZE: usize = RANGE.size_const();
+            /// assert_eq!(SIZE, 0x2000);
+            /// ```
+            #[inline]
+            pub const fn size_const(self) -> usize {
+                self.end.as_usize().wrapping_sub(self.start.as_usize())
+            }
+        }
+    };
+}
+
+impl_const_range!(VirtAddr, VirtAddrRange, "va_range");
+impl_const_range!(PhysAddr, PhysAddrRange, "pa_range");
+
 /// Converts the given range expression into [`AddrRange`]. Panics if the range
 /// is invalid.
 ///
@@ -449,7 +1036,7 @@ macro_rules! pa_range {
 
 #[cfg(test)]
 mod test {
-    use crate::{va, va_range, VirtAddrRange};
+    use crate::{overlaps_any, page_aligned_range_4k, va, VirtAddrRange};
 
     #[test]
     fn test_range_format() {
@@ -460,6 +1047,307 @@ mod test {
         assert_eq!(format!("{:X}", range), "VA:0xFEC000..VA:0xFFF000");
     }
 
+    #[test]
+    #[cfg(all(feature = "arbitrary", not(target_os = "none")))]
+    fn test_range_arbitrary() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let data = (0..=255u8).rev().cycle().take(4096).collect::<Vec<_>>();
+        let mut u = Unstructured::new(&data);
+        let mut count = 0;
+        while let Ok(range) = VirtAddrRange::arbitrary(&mut u) {
+            assert!(range.start <= range.end);
+            assert_eq!(VirtAddrRange::try_new(range.start, range.end), Some(range));
+            count += 1;
+            if u.is_empty() {
+                break;
+            }
+        }
+        assert!(count > 0);
+
+        // Endpoints are sorted.
+        let bytes = [0x20usize.to_le_bytes(), 0x10usize.to_le_bytes()].concat();
+        let range = VirtAddrRange::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        assert_eq!(range, va_range!(0x10..0x20));
+        let addr = crate::PhysAddr::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        assert_eq!(addr, crate::pa!(0x20));
+    }
+
+    #[test]
+    fn test_from_start_size_near_max() {
+        let top = va!(usize::MAX - 0xfff);
+        assert_eq!(
+            VirtAddrRange::try_from_start_size(top, 0xfff),
+            Some(va_range!(usize::MAX - 0xfff..usize::MAX))
+        );
+        assert_eq!(VirtAddrRange::try_from_start_size(top, 0x1000), None);
+        assert_eq!(VirtAddrRange::try_from_start_size(va!(1), usize::MAX), None);
+        assert_eq!(
+            VirtAddrRange::try_from_start_size(va!(0), usize::MAX),
+            Some(va_range!(0..usize::MAX))
+        );
+
+        const TOP: VirtAddrRange =
+            VirtAddrRange::from_start_size_const(va!(usize::MAX - 0xfff), 0xfff);
+        assert_eq!(TOP, VirtAddrRange::from_start_size(top, 0xfff));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_start_size_const_overflow() {
+        let _ = VirtAddrRange::from_start_size_const(va!(usize::MAX - 0xfff), 0x1000);
+    }
+
+    #[test]
+    fn test_size_const() {
+        const RANGE: VirtAddrRange = VirtAddrRange {
+            start: va!(0x1000),
+            end: va!(0x5000),
+        };
+        const SIZE: usize = RANGE.size_const();
+        assert_eq!(SIZE, 0x4000);
+        assert_eq!(SIZE, RANGE.size());
+        assert_eq!(va_range!(0x1000..0x1000).size_const(), 0);
+    }
+
+    #[test]
+    fn test_display_with_size() {
+        let range = va_range!(0x200000..0x400000);
+        assert_eq!(
+            format!("{}", range.display_with_size()),
+            "VA:0x200000..VA:0x400000 (2 MiB)"
+        );
+        let range = va_range!(0x1000..0x1800);
+        assert_eq!(
+            format!("{}", range.display_with_size()),
+            "VA:0x1000..VA:0x1800 (2 KiB)"
+        );
+        let range = va_range!(0x1000..0x1234);
+        assert_eq!(
+            format!("{}", range.display_with_size()),
+            "VA:0x1000..VA:0x1234 (564 B)"
+        );
+        let range = va_range!(0x1000..0x1000);
+        assert_eq!(
+            format!("{}", range.display_with_size()),
+            "VA:0x1000..VA:0x1000 (0 B)"
+        );
+        let range = va_range!(0..0x4000_0000);
+        assert_eq!(
+            format!("{}", range.display_with_size()),
+            "VA:0x0..VA:0x40000000 (1 GiB)"
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid `AddrRange`")]
+    fn test_invalid_range_size() {
+        let range = unsafe { VirtAddrRange::new_unchecked(va!(0x2000), va!(0x1000)) };
+        let _ = range.size();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "invalid `AddrRange`")]
+    fn test_invalid_range_overlaps() {
+        let range = unsafe { VirtAddrRange::new_unchecked(va!(0x2000), va!(0x1000)) };
+        let _ = va_range!(0..0x3000).overlaps(range);
+    }
+
+    #[test]
+    fn test_inclusive_conversion() {
+        let range = VirtAddrRange::from_inclusive(va!(0x1000), va!(0x1fff)).unwrap();
+        assert_eq!(range, va_range!(0x1000..0x2000));
+        assert_eq!(range.to_inclusive(), Some(va!(0x1000)..=va!(0x1fff)));
+
+        // A single byte.
+        let range = VirtAddrRange::from_inclusive(va!(0x1000), va!(0x1000)).unwrap();
+        assert_eq!(range.size(), 1);
+        assert_eq!(range.to_inclusive(), Some(va!(0x1000)..=va!(0x1000)));
+
+        assert_eq!(
+            VirtAddrRange::from_inclusive(va!(0x1001), va!(0x1000)),
+            None
+        );
+        assert_eq!(va_range!(0x1000..0x1000).to_inclusive(), None);
+
+        // The block whose last byte is at the top of the address space can not
+        // be represented, but the one right below it can.
+        let top = va!(usize::MAX - 0xfff);
+        assert_eq!(VirtAddrRange::from_inclusive(top, va!(usize::MAX)), None);
+        let range = VirtAddrRange::from_inclusive(top, va!(usize::MAX - 1)).unwrap();
+        assert_eq!(range.end, va!(usize::MAX));
+        assert_eq!(range.to_inclusive(), Some(top..=va!(usize::MAX - 1)));
+    }
+
+    #[test]
+    fn test_from_aligned() {
+        let from_aligned = VirtAddrRange::from_aligned;
+        assert_eq!(
+            from_aligned(va!(0x1000), 0x1000, 0x1000),
+            Some(va_range!(0x1000..0x2000))
+        );
+        assert_eq!(
+            from_aligned(va!(0x1001), 0x1, 0x1000),
+            Some(va_range!(0x2000..0x3000))
+        );
+        assert_eq!(
+            from_aligned(va!(0x1800), 0x2001, 0x1000),
+            Some(va_range!(0x2000..0x5000))
+        );
+        assert_eq!(
+            from_aligned(va!(0x1000), 0, 0x1000),
+            Some(va_range!(0x1000..0x1000))
+        );
+        assert_eq!(
+            from_aligned(va!(0x12_3456), 0x1000, 0x20_0000),
+            Some(va_range!(0x20_0000..0x40_0000))
+        );
+
+        // Overflow in aligning the start, the size, or computing the end.
+        assert_eq!(from_aligned(va!(usize::MAX - 1), 0x1000, 0x1000), None);
+        assert_eq!(from_aligned(va!(0x1000), usize::MAX - 1, 0x1000), None);
+        assert_eq!(from_aligned(va!(usize::MAX - 0xfff), 0x1000, 0x1000), None);
+        assert_eq!(
+            from_aligned(va!(usize::MAX - 0x1fff), 0x1000, 0x1000),
+            Some(va_range!(usize::MAX - 0x1fff..usize::MAX - 0xfff))
+        );
+    }
+
+    #[test]
+    fn test_dma_segments() {
+        // Crosses the 64K boundaries at 0x10000, 0x20000 and 0x30000.
+        let range = va_range!(0xe000..0x31000);
+        let segments = range.dma_segments(0x6000, 0x10000).collect::<Vec<_>>();
+        assert_eq!(
+            segments,
+            [
+                va_range!(0xe000..0x10000),
+                va_range!(0x10000..0x16000),
+                va_range!(0x16000..0x1c000),
+                va_range!(0x1c000..0x20000),
+                va_range!(0x20000..0x26000),
+                va_range!(0x26000..0x2c000),
+                va_range!(0x2c000..0x30000),
+                va_range!(0x30000..0x31000),
+            ]
+        );
+        for seg in &segments {
+            assert!(!seg.is_empty() && seg.size() <= 0x6000);
+            // Does not straddle a boundary.
+            assert_eq!(seg.start.as_usize() >> 16, (seg.end.as_usize() - 1) >> 16);
+        }
+        assert_eq!(
+            segments.iter().map(|seg| seg.size()).sum::<usize>(),
+            range.size()
+        );
+
+        // Unaligned and small.
+        assert_eq!(
+            va_range!(0x1234..0x1300)
+                .dma_segments(0x1000, 0x1000)
+                .collect::<Vec<_>>(),
+            [va_range!(0x1234..0x1300)]
+        );
+        assert_eq!(
+            va_range!(0x1000..0x1000)
+                .dma_segments(0x1000, 0x1000)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_range_ordering() {
+        let mut ranges = vec![
+            va_range!(0x3000..0x4000),
+            va_range!(0x1000..0x3000),
+            va_range!(0x2000..0x2000),
+            va_range!(0x1000..0x2000),
+            va_range!(0..0x8000),
+        ];
+        ranges.sort();
+        assert_eq!(
+            ranges,
+            [
+                va_range!(0..0x8000),
+                va_range!(0x1000..0x2000),
+                va_range!(0x1000..0x3000),
+                va_range!(0x2000..0x2000),
+                va_range!(0x3000..0x4000),
+            ]
+        );
+        assert!(va_range!(0x1000..0x2000) < va_range!(0x1000..0x2001));
+        assert_eq!(
+            va_range!(0x1000..0x2000).cmp(&va_range!(0x1000..0x2000)),
+            core::cmp::Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_contains_range_inclusive() {
+        let range = va_range!(0x1000..0x2000);
+        for (other, exclusive, inclusive) in [
+            (va_range!(0x1000..0x2000), true, false),
+            (va_range!(0x1000..0x1fff), true, true),
+            (va_range!(0x1fff..0x1fff), true, true),
+            (va_range!(0x2000..0x2000), true, false),
+            (va_range!(0x0fff..0x1fff), false, false),
+            (va_range!(0x1800..0x2001), false, false),
+        ] {
+            assert_eq!(range.contains_range(other), exclusive, "{other:?}");
+            assert_eq!(
+                range.contains_range_inclusive(other),
+                inclusive,
+                "{other:?}"
+            );
+        }
+        // An empty range contains no inclusive range.
+        let empty = va_range!(0x1000..0x1000);
+        assert!(!empty.contains_range_inclusive(empty));
+        assert!(empty.contains_range(empty));
+    }
+
+    #[test]
+    fn test_split_by_alignment() {
+        let split =
+            |range: VirtAddrRange, align| range.split_by_alignment(align).collect::<Vec<_>>();
+
+        assert_eq!(
+            split(va_range!(0x1800..0x4200), 0x1000),
+            [
+                va_range!(0x1800..0x2000),
+                va_range!(0x2000..0x3000),
+                va_range!(0x3000..0x4000),
+                va_range!(0x4000..0x4200),
+            ]
+        );
+        // Aligned head or tail.
+        assert_eq!(
+            split(va_range!(0x1000..0x2800), 0x1000),
+            [va_range!(0x1000..0x2000), va_range!(0x2000..0x2800)]
+        );
+        assert_eq!(
+            split(va_range!(0x1800..0x3000), 0x1000),
+            [va_range!(0x1800..0x2000), va_range!(0x2000..0x3000)]
+        );
+        // Within a single aligned block.
+        assert_eq!(
+            split(va_range!(0x1100..0x1200), 0x1000),
+            [va_range!(0x1100..0x1200)]
+        );
+        assert!(split(va_range!(0x1100..0x1100), 0x1000).is_empty());
+        // Up to the top of the address space.
+        assert_eq!(
+            split(va_range!(usize::MAX - 0x17ff..usize::MAX), 0x1000),
+            [
+                va_range!(usize::MAX - 0x17ff..usize::MAX - 0xfff),
+                va_range!(usize::MAX - 0xfff..usize::MAX),
+            ]
+        );
+    }
+
     #[test]
     fn test_range() {
         let start = va!(0x1000);
@@ -509,4 +1397,113 @@ mod test {
         assert_eq!(default_range.start, va!(0));
         assert_eq!(default_range.end, va!(0));
     }
+
+    #[test]
+    fn test_intersect() {
+        let range = va_range!(0x1000..0x3000);
+        assert_eq!(range.intersect(range), Some(range));
+        assert_eq!(
+            range.intersect(va_range!(0..0x1800)),
+            Some(va_range!(0x1000..0x1800))
+        );
+        assert_eq!(
+            range.intersect(va_range!(0x1800..0x2000)),
+            Some(va_range!(0x1800..0x2000))
+        );
+        assert_eq!(
+            range.intersect(va_range!(0..0x4000)),
+            Some(va_range!(0x1000..0x3000))
+        );
+        assert_eq!(range.intersect(va_range!(0..0x1000)), None);
+        assert_eq!(range.intersect(va_range!(0x3000..0x4000)), None);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_subtract_all() {
+        let range = va_range!(0x1000..0x10000);
+        assert_eq!(range.subtract_all(&[]), [range]);
+        assert_eq!(
+            range.subtract_all(&[va_range!(0x4000..0x5000), va_range!(0x8000..0xa000)]),
+            [
+                va_range!(0x1000..0x4000),
+                va_range!(0x5000..0x8000),
+                va_range!(0xa000..0x10000),
+            ]
+        );
+        // Unsorted, overlapping, adjacent, empty and out-of-range holes.
+        assert_eq!(
+            range.subtract_all(&[
+                va_range!(0x6000..0x7000),
+                va_range!(0x2000..0x4000),
+                va_range!(0x3000..0x5000),
+                va_range!(0x5000..0x6000),
+                va_range!(0x9000..0x9000),
+                va_range!(0..0x1800),
+                va_range!(0xf000..0x20000),
+            ]),
+            [va_range!(0x1800..0x2000), va_range!(0x7000..0xf000)]
+        );
+        assert!(range.subtract_all(&[va_range!(0..0x20000)]).is_empty());
+    }
+
+    #[test]
+    fn test_overlaps_any() {
+        let reserved = [va_range!(0x1000..0x2000), va_range!(0x4000..0x5000)];
+        assert!(overlaps_any(va_range!(0x1fff..0x2000), &reserved));
+        assert!(overlaps_any(va_range!(0x3000..0x4001), &reserved));
+        assert!(overlaps_any(va_range!(0..0x10000), &reserved));
+        assert!(!overlaps_any(va_range!(0x2000..0x4000), &reserved));
+        assert!(!overlaps_any(va_range!(0x5000..0x9000), &reserved));
+        assert!(!overlaps_any(va_range!(0x1000..0x2000), &[]));
+    }
+
+    #[test]
+    fn test_page_aligned_range_4k() {
+        // Aligned start and size.
+        assert_eq!(
+            page_aligned_range_4k(va!(0x1000), 0x1000),
+            va_range!(0x1000..0x2000)
+        );
+        // Unaligned start.
+        assert_eq!(
+            page_aligned_range_4k(va!(0x1234), 0x1000),
+            va_range!(0x1000..0x3000)
+        );
+        // Unaligned size.
+        assert_eq!(
+            page_aligned_range_4k(va!(0x1000), 0x1),
+            va_range!(0x1000..0x2000)
+        );
+        // Unaligned start and size.
+        assert_eq!(
+            page_aligned_range_4k(va!(0x1fff), 0x2),
+            va_range!(0x1000..0x3000)
+        );
+        // Empty region.
+        assert!(page_aligned_range_4k(va!(0x2000), 0).is_empty());
+        assert_eq!(
+            page_aligned_range_4k(va!(0x1800), 0),
+            va_range!(0x1000..0x2000)
+        );
+    }
+
+    #[test]
+    fn test_map_addr() {
+        let range = va_range!(0x1000..0x3000);
+        let offset = 0x8000_0000usize;
+
+        let mapped = range.map_addr(|addr| addr + offset);
+        assert_eq!(mapped.start, va!(0x8000_1000));
+        assert_eq!(mapped.end, va!(0x8000_3000));
+        assert_eq!(mapped.size(), range.size());
+        assert_eq!(mapped.map_addr(|addr| addr - offset), range);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_map_addr_invalid() {
+        let range = va_range!(0x1000..0x3000);
+        let _ = range.map_addr(|addr| va!(0x4000 - addr.as_usize()));
+    }
 }
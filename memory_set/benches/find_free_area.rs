@@ -0,0 +1,88 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use memory_addr::{va, va_range, MemoryAddr, VirtAddr, VirtAddrRange};
+use memory_set::{MappingBackend, MemoryArea, MemorySet};
+
+const NUM_AREAS: usize = 10_000;
+const AREA_SIZE: usize = 0x1000;
+
+/// A backend that does nothing.
+#[derive(Clone)]
+struct NopBackend;
+
+impl MappingBackend for NopBackend {
+    type Addr = VirtAddr;
+    type Flags = u8;
+    type PageTable = ();
+    type Error = ();
+
+    fn map(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn unmap(&self, _: VirtAddr, _: usize, _: &mut ()) -> Result<(), ()> {
+        Ok(())
+    }
+
+    fn protect(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+/// Maps `NUM_AREAS` areas of `AREA_SIZE` bytes, with a gap of `AREA_SIZE`
+/// bytes between each two of them.
+fn build_set() -> MemorySet<NopBackend> {
+    let mut set = MemorySet::new();
+    for i in 0..NUM_AREAS {
+        let area = MemoryArea::new(va!(i * AREA_SIZE * 2), AREA_SIZE, 0, NopBackend);
+        set.map(area, &mut (), false).unwrap();
+    }
+    set
+}
+
+/// The previous approach, which scans all areas from the lowest one.
+fn find_free_area_scan(
+    set: &MemorySet<NopBackend>,
+    hint: VirtAddr,
+    size: usize,
+    limit: VirtAddrRange,
+) -> Option<VirtAddr> {
+    let mut last_end = hint.max(limit.start);
+    for area in set.iter() {
+        if let Some(end) = last_end.checked_add(size) {
+            if end <= area.start() && end <= limit.end {
+                return Some(last_end);
+            }
+        }
+        last_end = last_end.max(area.end());
+    }
+    last_end
+        .checked_add(size)
+        .is_some_and(|end| end <= limit.end)
+        .then_some(last_end)
+}
+
+fn bench_find_free_area(c: &mut Criterion) {
+    let set = build_set();
+    let limit = va_range!(0..usize::MAX);
+    // No gap is large enough, except the one after the last area. The hint is
+    // near the top of the mapped space.
+    let hint = va!((NUM_AREAS - 10) * AREA_SIZE * 2);
+    let size = AREA_SIZE * 2;
+    let expected = Some(va!((NUM_AREAS * 2 - 1) * AREA_SIZE));
+    assert_eq!(set.find_free_area(hint, size, limit, 1), expected);
+    assert_eq!(find_free_area_scan(&set, hint, size, limit), expected);
+
+    let mut group = c.benchmark_group("find_free_area_10k");
+    group.bench_function("from_hint", |b| {
+        b.iter(|| set.find_free_area(black_box(hint), size, limit, 1))
+    });
+    group.bench_function("scan_all", |b| {
+        b.iter(|| find_free_area_scan(&set, black_box(hint), size, limit))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_find_free_area);
+criterion_main!(benches);
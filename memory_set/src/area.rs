@@ -9,23 +9,172 @@ use crate::{MappingBackend, MappingError, MappingResult};
 ///
 /// The target physical memory frames are determined by [`MappingBackend`] and
 /// may not be contiguous.
-pub struct MemoryArea<B: MappingBackend> {
+///
+/// Each area also carries user metadata of type `M` (e.g., a file handle or a
+/// name), which defaults to `()`.
+///
+/// The range of an area is an [`AddrRange`] with an exclusive end, so an area
+/// can not cover the top page of the address space. Use
+/// [`MemoryArea::try_new`] to reject such areas with
+/// [`MappingError::InvalidParam`] instead of panicking.
+pub struct MemoryArea<B: MappingBackend, M = ()> {
     va_range: AddrRange<B::Addr>,
     flags: B::Flags,
     backend: B,
+    metadata: M,
 }
 
 impl<B: MappingBackend> MemoryArea<B> {
-    /// Creates a new memory area.
+    /// Creates a new memory area without metadata.
     ///
     /// # Panics
     ///
-    /// Panics if `start + size` overflows.
+    /// Panics if `start + size` overflows, e.g., for a huge `size` near the
+    /// top of the address space. Use [`MemoryArea::try_new`] to handle it as
+    /// an error instead.
     pub fn new(start: B::Addr, size: usize, flags: B::Flags, backend: B) -> Self {
+        Self::with_metadata(start, size, flags, backend, ())
+    }
+
+    /// Creates a new memory area without metadata from the given address
+    /// range.
+    ///
+    /// It is useful when the range has already been validated (e.g., found by
+    /// [`MemorySet::find_free_area`](crate::MemorySet::find_free_area)), so
+    /// that the size need not be computed and checked again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, va_range, VirtAddr};
+    /// use memory_set::{MappingBackend, MemoryArea};
+    ///
+    /// #[derive(Clone)]
+    /// struct DummyBackend;
+    ///
+    /// impl MappingBackend for DummyBackend {
+    ///     type Addr = VirtAddr;
+    ///     type Flags = u8;
+    ///     type PageTable = ();
+    ///     type Error = ();
+    /// #   fn map(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn unmap(&self, _: VirtAddr, _: usize, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn protect(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    ///     // ...
+    /// }
+    ///
+    /// let area = MemoryArea::from_range(va_range!(0x1000..0x3000), 1, DummyBackend);
+    /// assert_eq!(area.start(), va!(0x1000));
+    /// assert_eq!(area.size(), 0x2000);
+    /// ```
+    pub fn from_range(va_range: AddrRange<B::Addr>, flags: B::Flags, backend: B) -> Self {
+        Self {
+            va_range,
+            flags,
+            backend,
+            metadata: (),
+        }
+    }
+
+    /// Creates a new memory area, checking that `start` and `size` are aligned
+    /// to the [page size](MappingBackend::page_size) of the backend.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if they are not aligned, or
+    /// `start + size` overflows.
+    ///
+    /// Unlike [`MemoryArea::new`], which remains the panicking convenience
+    /// constructor, it never panics, so it is suitable for untrusted sizes
+    /// (e.g., from user space or firmware tables).
+    pub fn try_new(
+        start: B::Addr,
+        size: usize,
+        flags: B::Flags,
+        backend: B,
+    ) -> MappingResult<Self, B::Error> {
+        let page_size = backend.page_size();
+        if !start.is_aligned(page_size) || !size.is_aligned(page_size) {
+            return Err(MappingError::InvalidParam);
+        }
+        let va_range =
+            AddrRange::try_from_start_size(start, size).ok_or(MappingError::InvalidParam)?;
+        Ok(Self {
+            va_range,
+            flags,
+            backend,
+            metadata: (),
+        })
+    }
+
+    /// Returns a [`MemoryAreaBuilder`] for constructing a memory area with the
+    /// given backend step by step.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, va_range, VirtAddr};
+    /// use memory_set::{MappingBackend, MappingError, MemoryArea};
+    ///
+    /// #[derive(Clone)]
+    /// struct DummyBackend;
+    ///
+    /// impl MappingBackend for DummyBackend {
+    ///     type Addr = VirtAddr;
+    ///     type Flags = u8;
+    ///     type PageTable = ();
+    ///     type Error = ();
+    ///
+    ///     fn page_size(&self) -> usize {
+    ///         0x1000
+    ///     }
+    /// #   fn map(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn unmap(&self, _: VirtAddr, _: usize, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn protect(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    ///     // ...
+    /// }
+    ///
+    /// let area = MemoryArea::builder(DummyBackend)
+    ///     .start(va!(0x1000))
+    ///     .size(0x2000)
+    ///     .flags(1)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(area.va_range(), va_range!(0x1000..0x3000));
+    ///
+    /// let res = MemoryArea::builder(DummyBackend)
+    ///     .start(va!(0x1800))
+    ///     .size(0x2000)
+    ///     .flags(1)
+    ///     .build();
+    /// assert_eq!(res.err(), Some(MappingError::InvalidParam));
+    /// ```
+    pub fn builder(backend: B) -> MemoryAreaBuilder<B> {
+        MemoryAreaBuilder {
+            start: None,
+            size: None,
+            flags: None,
+            backend,
+        }
+    }
+}
+
+impl<B: MappingBackend, M> MemoryArea<B, M> {
+    /// Creates a new memory area with the given metadata.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start + size` overflows.
+    pub fn with_metadata(
+        start: B::Addr,
+        size: usize,
+        flags: B::Flags,
+        backend: B,
+        metadata: M,
+    ) -> Self {
         Self {
             va_range: AddrRange::from_start_size(start, size),
             flags,
             backend,
+            metadata,
         }
     }
 
@@ -58,9 +207,35 @@ impl<B: MappingBackend> MemoryArea<B> {
     pub const fn backend(&self) -> &B {
         &self.backend
     }
+
+    /// Returns the user metadata of the memory area.
+    pub const fn metadata(&self) -> &M {
+        &self.metadata
+    }
+
+    /// Returns a mutable reference to the flags of the memory area.
+    ///
+    /// **It does not update the page table.** It is for flags that are not
+    /// reflected in the page table (e.g., software-only annotations), or for
+    /// callers that manage the mappings themselves. Use
+    /// [`MemoryArea::update_flags`] to keep the page table in sync.
+    pub fn flags_mut(&mut self) -> &mut B::Flags {
+        &mut self.flags
+    }
+
+    /// Returns a mutable reference to the user metadata of the memory area.
+    pub fn metadata_mut(&mut self) -> &mut M {
+        &mut self.metadata
+    }
+
+    /// Returns the part of the given address range that falls within the
+    /// memory area, or `None` if they do not overlap.
+    pub fn clip(&self, range: AddrRange<B::Addr>) -> Option<AddrRange<B::Addr>> {
+        self.va_range.intersect(range)
+    }
 }
 
-impl<B: MappingBackend> MemoryArea<B> {
+impl<B: MappingBackend, M> MemoryArea<B, M> {
     /// Changes the flags.
     pub(crate) fn set_flags(&mut self, new_flags: B::Flags) {
         self.flags = new_flags;
@@ -72,19 +247,102 @@ impl<B: MappingBackend> MemoryArea<B> {
     }
 
     /// Maps the whole memory area in the page table.
-    pub(crate) fn map_area(&self, page_table: &mut B::PageTable) -> MappingResult {
+    pub(crate) fn map_area(&self, page_table: &mut B::PageTable) -> MappingResult<(), B::Error> {
         self.backend
-            .map(self.start(), self.size(), self.flags, page_table)
-            .then_some(())
-            .ok_or(MappingError::BadState)
+            .map_each(self.va_range, self.flags, page_table)
+            .map_err(MappingError::BadState)
     }
 
     /// Unmaps the whole memory area in the page table.
-    pub(crate) fn unmap_area(&self, page_table: &mut B::PageTable) -> MappingResult {
+    pub(crate) fn unmap_area(&self, page_table: &mut B::PageTable) -> MappingResult<(), B::Error> {
         self.backend
             .unmap(self.start(), self.size(), page_table)
-            .then_some(())
-            .ok_or(MappingError::BadState)
+            .map_err(MappingError::BadState)
+    }
+
+    /// Extends the memory area at the right side.
+    ///
+    /// The end address of the memory area is increased by `add_size`. The
+    /// added part `[end, end + add_size)` is mapped with the current flags.
+    ///
+    /// The added part must not overlap with any other mapping, which is the
+    /// caller's responsibility. If the backend fails to map it, the memory area
+    /// is left unchanged.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if `add_size` is not aligned to
+    /// the [page size](MappingBackend::page_size) of the backend, or the new
+    /// end address overflows.
+    pub fn extend_right(
+        &mut self,
+        add_size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        if !add_size.is_aligned(self.backend.page_size()) {
+            return Err(MappingError::InvalidParam);
+        }
+        let new_end = self
+            .end()
+            .checked_add(add_size)
+            .ok_or(MappingError::InvalidParam)?;
+        self.backend
+            .map_each(AddrRange::new(self.end(), new_end), self.flags, page_table)
+            .map_err(MappingError::BadState)?;
+        self.va_range.end = new_end;
+        Ok(())
+    }
+
+    /// Extends the memory area at the left side.
+    ///
+    /// The start address of the memory area is decreased by `add_size`. The
+    /// added part `[start - add_size, start)` is mapped with the current flags.
+    ///
+    /// The added part must not overlap with any other mapping, and merging with
+    /// a lower neighbor is not done here; both are the caller's
+    /// responsibility. If the backend fails to map it, the memory area is left
+    /// unchanged.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if `add_size` is not aligned to
+    /// the [page size](MappingBackend::page_size) of the backend, or the new
+    /// start address underflows.
+    pub fn extend_left(
+        &mut self,
+        add_size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        if !add_size.is_aligned(self.backend.page_size()) {
+            return Err(MappingError::InvalidParam);
+        }
+        let new_start = self
+            .start()
+            .checked_sub(add_size)
+            .ok_or(MappingError::InvalidParam)?;
+        self.backend
+            .map_each(
+                AddrRange::new(new_start, self.start()),
+                self.flags,
+                page_table,
+            )
+            .map_err(MappingError::BadState)?;
+        self.va_range.start = new_start;
+        Ok(())
+    }
+
+    /// Moves the whole memory area to `new_start` in the page table.
+    ///
+    /// The memory area is left unchanged on failure, relying on
+    /// [`MappingBackend::remap`] to keep the old region mapped in that case.
+    pub(crate) fn remap_area(
+        &mut self,
+        new_start: B::Addr,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        let new_range = AddrRange::try_from_start_size(new_start, self.size())
+            .ok_or(MappingError::InvalidParam)?;
+        self.backend
+            .remap(self.start(), new_start, self.size(), self.flags, page_table)
+            .map_err(MappingError::BadState)?;
+        self.va_range = new_range;
+        Ok(())
     }
 
     /// Changes the flags in the page table.
@@ -92,9 +350,24 @@ impl<B: MappingBackend> MemoryArea<B> {
         &mut self,
         new_flags: B::Flags,
         page_table: &mut B::PageTable,
-    ) -> MappingResult {
+    ) -> MappingResult<(), B::Error> {
         self.backend
-            .protect(self.start(), self.size(), new_flags, page_table);
+            .protect(self.start(), self.size(), new_flags, page_table)
+            .map_err(MappingError::BadState)
+    }
+
+    /// Changes the flags of the memory area, and updates the page table
+    /// accordingly.
+    ///
+    /// The page table is updated first, so the flags of the memory area are
+    /// left unchanged on failure.
+    pub fn update_flags(
+        &mut self,
+        new_flags: B::Flags,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        self.protect_area(new_flags, page_table)?;
+        self.set_flags(new_flags);
         Ok(())
     }
 
@@ -108,15 +381,15 @@ impl<B: MappingBackend> MemoryArea<B> {
         &mut self,
         new_size: usize,
         page_table: &mut B::PageTable,
-    ) -> MappingResult {
+    ) -> MappingResult<(), B::Error> {
         assert!(new_size > 0 && new_size < self.size());
 
         let old_size = self.size();
         let unmap_size = old_size - new_size;
 
-        if !self.backend.unmap(self.start(), unmap_size, page_table) {
-            return Err(MappingError::BadState);
-        }
+        self.backend
+            .unmap(self.start(), unmap_size, page_table)
+            .map_err(MappingError::BadState)?;
         // Use wrapping_add to avoid overflow check.
         // Safety: `unmap_size` is less than the current size, so it will never
         // overflow.
@@ -134,7 +407,7 @@ impl<B: MappingBackend> MemoryArea<B> {
         &mut self,
         new_size: usize,
         page_table: &mut B::PageTable,
-    ) -> MappingResult {
+    ) -> MappingResult<(), B::Error> {
         assert!(new_size > 0 && new_size < self.size());
         let old_size = self.size();
         let unmap_size = old_size - new_size;
@@ -143,9 +416,9 @@ impl<B: MappingBackend> MemoryArea<B> {
         // Safety: `new_size` is less than the current size, so it will never overflow.
         let unmap_start = self.start().wrapping_add(new_size);
 
-        if !self.backend.unmap(unmap_start, unmap_size, page_table) {
-            return Err(MappingError::BadState);
-        }
+        self.backend
+            .unmap(unmap_start, unmap_size, page_table)
+            .map_err(MappingError::BadState)?;
 
         // Use wrapping_sub to avoid overflow check, same as above.
         self.va_range.end = self.va_range.end.wrapping_sub(unmap_size);
@@ -157,17 +430,62 @@ impl<B: MappingBackend> MemoryArea<B> {
     /// The original memory area is shrunk to the left part, and the right part
     /// is returned.
     ///
-    /// Returns `None` if the given position is not in the memory area, or one
-    /// of the parts is empty after splitting.
-    pub(crate) fn split(&mut self, pos: B::Addr) -> Option<Self> {
-        if self.start() < pos && pos < self.end() {
-            let new_area = Self::new(
+    /// Returns `None` if the given position is not in the memory area, one of
+    /// the parts is empty after splitting, or the position is not aligned to
+    /// the backend's [page size](MappingBackend::page_size) (splitting a
+    /// huge-page mapping in the middle would leave neither part able to unmap
+    /// or protect it correctly).
+    ///
+    /// The metadata is cloned into the right part.
+    ///
+    /// Only the bookkeeping is changed: the underlying mappings in the page
+    /// table are left as they are, and both parts share a clone of the
+    /// backend.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use memory_addr::{va, va_range, VirtAddr};
+    /// use memory_set::{MappingBackend, MemoryArea};
+    ///
+    /// #[derive(Clone)]
+    /// struct DummyBackend;
+    ///
+    /// impl MappingBackend for DummyBackend {
+    ///     type Addr = VirtAddr;
+    ///     type Flags = u8;
+    ///     type PageTable = ();
+    ///     type Error = ();
+    /// #   fn map(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn unmap(&self, _: VirtAddr, _: usize, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    /// #   fn protect(&self, _: VirtAddr, _: usize, _: u8, _: &mut ()) -> Result<(), ()> { Ok(()) }
+    ///     // ...
+    /// }
+    ///
+    /// // Split a 4-page area in half.
+    /// let mut area = MemoryArea::new(va!(0x1000), 0x4000, 1, DummyBackend);
+    /// let right = area.split(va!(0x3000)).unwrap();
+    /// assert_eq!(area.va_range(), va_range!(0x1000..0x3000));
+    /// assert_eq!(right.va_range(), va_range!(0x3000..0x5000));
+    /// assert_eq!(right.flags(), 1);
+    ///
+    /// // The position must be strictly inside the area.
+    /// assert!(area.split(va!(0x1000)).is_none());
+    /// assert!(area.split(va!(0x3000)).is_none());
+    /// ```
+    pub fn split(&mut self, pos: B::Addr) -> Option<Self>
+    where
+        M: Clone,
+    {
+        if self.start() < pos && pos < self.end() && pos.is_aligned(self.backend.page_size()) {
+            let new_area = Self::with_metadata(
                 pos,
                 // Use wrapping_sub_addr to avoid overflow check. It is safe because
                 // `pos` is within the memory area.
                 self.end().wrapping_sub_addr(pos),
                 self.flags,
                 self.backend.clone(),
+                self.metadata.clone(),
             );
             self.va_range.end = pos;
             Some(new_area)
@@ -177,7 +495,71 @@ impl<B: MappingBackend> MemoryArea<B> {
     }
 }
 
-impl<B: MappingBackend> fmt::Debug for MemoryArea<B>
+/// A builder for [`MemoryArea`], created by [`MemoryArea::builder`].
+///
+/// The start address, size and flags must all be set before calling
+/// [`MemoryAreaBuilder::build`].
+pub struct MemoryAreaBuilder<B: MappingBackend> {
+    start: Option<B::Addr>,
+    size: Option<usize>,
+    flags: Option<B::Flags>,
+    backend: B,
+}
+
+impl<B: MappingBackend> MemoryAreaBuilder<B> {
+    /// Sets the start address of the area.
+    pub fn start(mut self, start: B::Addr) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    /// Sets the size of the area in bytes.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the mapping flags of the area.
+    pub fn flags(mut self, flags: B::Flags) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Builds the memory area, with the same checks as
+    /// [`MemoryArea::try_new`].
+    ///
+    /// Returns [`MappingError::InvalidParam`] if the start address, size or
+    /// flags are not set, if the start address or size is not aligned to the
+    /// [page size](MappingBackend::page_size) of the backend, or if
+    /// `start + size` overflows.
+    pub fn build(self) -> MappingResult<MemoryArea<B>, B::Error> {
+        match (self.start, self.size, self.flags) {
+            (Some(start), Some(size), Some(flags)) => {
+                MemoryArea::try_new(start, size, flags, self.backend)
+            }
+            _ => Err(MappingError::InvalidParam),
+        }
+    }
+}
+
+/// Cloning a memory area only copies its range, flags, backend and metadata.
+///
+/// **It does not touch any page table.** The clone describes the same mappings
+/// as the original one, so mapping it into the same page table again is a
+/// double-map. Mapping the clone (e.g., into another page table) is the
+/// caller's responsibility.
+impl<B: MappingBackend, M: Clone> Clone for MemoryArea<B, M> {
+    fn clone(&self) -> Self {
+        Self {
+            va_range: self.va_range,
+            flags: self.flags,
+            backend: self.backend.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+impl<B: MappingBackend, M> fmt::Debug for MemoryArea<B, M>
 where
     B::Addr: fmt::Debug,
     B::Flags: fmt::Debug + Copy,
@@ -1,4 +1,4 @@
-use memory_addr::MemoryAddr;
+use memory_addr::{AddrRange, MemoryAddr};
 
 /// Underlying operations to do when manipulating mappings within the specific
 /// [`MemoryArea`](crate::MemoryArea).
@@ -7,6 +7,14 @@ use memory_addr::MemoryAddr;
 /// mappings, the target physical address is known when it is added to the page
 /// table. For lazy mappings, an empty mapping needs to be added to the page
 /// table to trigger a page fault.
+///
+/// Each operation returns a [`Result`] with the backend-specific
+/// [`Error`](MappingBackend::Error) type, which is reported to the caller of
+/// [`MemorySet`](crate::MemorySet) as [`MappingError::BadState`]. Simple
+/// backends that have nothing to report can use `()` as the error type, and
+/// return `Ok(())` or `Err(())`.
+///
+/// [`MappingError::BadState`]: crate::MappingError::BadState
 pub trait MappingBackend: Clone {
     /// The address type used in the memory area.
     type Addr: MemoryAddr;
@@ -14,6 +22,17 @@ pub trait MappingBackend: Clone {
     type Flags: Copy;
     /// The page table type used in the memory area.
     type PageTable;
+    /// The error type returned by the operations of the backend.
+    type Error;
+
+    /// The page size used by the backend, which must be a power of two.
+    ///
+    /// The start address and size of every memory area (and of every region
+    /// passed to the operations) are expected to be aligned to it. The default
+    /// implementation returns 1, i.e., no alignment is required.
+    fn page_size(&self) -> usize {
+        1
+    }
 
     /// What to do when mapping a region within the area with the given flags.
     fn map(
@@ -22,10 +41,15 @@ pub trait MappingBackend: Clone {
         size: usize,
         flags: Self::Flags,
         page_table: &mut Self::PageTable,
-    ) -> bool;
+    ) -> Result<(), Self::Error>;
 
     /// What to do when unmaping a memory region within the area.
-    fn unmap(&self, start: Self::Addr, size: usize, page_table: &mut Self::PageTable) -> bool;
+    fn unmap(
+        &self,
+        start: Self::Addr,
+        size: usize,
+        page_table: &mut Self::PageTable,
+    ) -> Result<(), Self::Error>;
 
     /// What to do when changing access flags.
     fn protect(
@@ -34,5 +58,104 @@ pub trait MappingBackend: Clone {
         size: usize,
         new_flags: Self::Flags,
         page_table: &mut Self::PageTable,
-    ) -> bool;
+    ) -> Result<(), Self::Error>;
+
+    /// Maps the given `range`, which is what
+    /// [`MemoryArea`](crate::MemoryArea) (and thus
+    /// [`MemorySet`](crate::MemorySet)) calls whenever a new region is mapped,
+    /// e.g., in `map`, `map_many` and `resize`.
+    ///
+    /// The default implementation maps the whole range with a single [`map`]
+    /// call. Backends that map each page individually (e.g., lazy mappings)
+    /// can override it with [`map_each_page`] to catch per-page failures.
+    ///
+    /// [`map`]: MappingBackend::map
+    /// [`map_each_page`]: MappingBackend::map_each_page
+    fn map_each(
+        &self,
+        range: AddrRange<Self::Addr>,
+        flags: Self::Flags,
+        page_table: &mut Self::PageTable,
+    ) -> Result<(), Self::Error> {
+        self.map(range.start, range.size(), flags, page_table)
+    }
+
+    /// Maps the given `range` page by page, calling [`map`] once for every
+    /// [`page_size`] bytes.
+    ///
+    /// With the default page size of 1, this calls [`map`] once per byte, so
+    /// backends using it should override [`page_size`] as well.
+    ///
+    /// If mapping a page fails, the pages already mapped by this call are
+    /// unmapped again and the error is returned. If that rollback fails as
+    /// well, the error of [`unmap`] is returned instead, and the pages
+    /// already mapped may be left in the page table.
+    ///
+    /// [`map`]: MappingBackend::map
+    /// [`unmap`]: MappingBackend::unmap
+    /// [`page_size`]: MappingBackend::page_size
+    fn map_each_page(
+        &self,
+        range: AddrRange<Self::Addr>,
+        flags: Self::Flags,
+        page_table: &mut Self::PageTable,
+    ) -> Result<(), Self::Error> {
+        let size = range.size();
+        let page_size = self.page_size();
+        let mut mapped = 0;
+        while mapped < size {
+            let chunk = page_size.min(size - mapped);
+            if let Err(e) = self.map(range.start.add(mapped), chunk, flags, page_table) {
+                if mapped > 0 {
+                    self.unmap(range.start, mapped, page_table)?;
+                }
+                return Err(e);
+            }
+            mapped += chunk;
+        }
+        Ok(())
+    }
+
+    /// What to do when moving a mapped region of `size` bytes from `old_start`
+    /// to `new_start`, keeping the given flags.
+    ///
+    /// The two regions may overlap. On failure, the old region should be left
+    /// mapped as before, since the memory area stays at its old position.
+    ///
+    /// The default implementation unmaps the old region and then maps the new
+    /// one, mapping the old region back if the latter fails. Backends that can
+    /// move page table entries cheaply (or need to preserve their state)
+    /// should override it.
+    fn remap(
+        &self,
+        old_start: Self::Addr,
+        new_start: Self::Addr,
+        size: usize,
+        flags: Self::Flags,
+        page_table: &mut Self::PageTable,
+    ) -> Result<(), Self::Error> {
+        self.unmap(old_start, size, page_table)?;
+        if let Err(e) = self.map(new_start, size, flags, page_table) {
+            self.map(old_start, size, flags, page_table)?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// What to do after a high-level operation of
+    /// [`MemorySet`](crate::MemorySet) (e.g., `map`, `unmap` or `protect`) has
+    /// changed the page table, e.g., flushing the TLB.
+    ///
+    /// It is called once per operation with the overall `affected` range, so
+    /// the backend can do a single TLB shootdown instead of one per area. The
+    /// default implementation does nothing.
+    fn finish_batch(&self, _affected: AddrRange<Self::Addr>, _page_table: &mut Self::PageTable) {}
+
+    /// Whether an area with this backend can be merged with an adjacent area
+    /// with the `other` backend, provided that their flags are the same.
+    ///
+    /// The default implementation always returns `true`.
+    fn mergeable(&self, _other: &Self) -> bool {
+        true
+    }
 }
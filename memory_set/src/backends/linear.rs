@@ -0,0 +1,110 @@
+use core::marker::PhantomData;
+
+use memory_addr::{MemoryAddr, PhysAddr};
+
+use crate::MappingBackend;
+
+/// Page table operations required by [`LinearBackend`].
+pub trait LinearPageTable {
+    /// The virtual address type used in the page table.
+    type Addr: MemoryAddr;
+    /// The flags type used in the page table.
+    type Flags: Copy;
+
+    /// Maps the virtual memory region `[vaddr, vaddr + size)` to the physical
+    /// memory region `[paddr, paddr + size)` with the given flags.
+    fn map_region(
+        &mut self,
+        vaddr: Self::Addr,
+        paddr: PhysAddr,
+        size: usize,
+        flags: Self::Flags,
+    ) -> bool;
+
+    /// Unmaps the virtual memory region `[vaddr, vaddr + size)`.
+    fn unmap_region(&mut self, vaddr: Self::Addr, size: usize) -> bool;
+
+    /// Changes the flags of the virtual memory region `[vaddr, vaddr + size)`.
+    fn protect_region(&mut self, vaddr: Self::Addr, size: usize, new_flags: Self::Flags) -> bool;
+}
+
+/// A linear mapping backend.
+///
+/// The virtual address `va` is mapped to the physical address `va -
+/// phys_offset`, i.e., `phys_offset` is the difference between a virtual
+/// address and its target physical address. The page table operations are
+/// delegated to the page table type `P`, which implements [`LinearPageTable`].
+pub struct LinearBackend<P: LinearPageTable> {
+    phys_offset: usize,
+    _phantom: PhantomData<fn(&mut P)>,
+}
+
+impl<P: LinearPageTable> LinearBackend<P> {
+    /// Creates a new linear mapping backend with the given offset.
+    pub const fn new(phys_offset: usize) -> Self {
+        Self {
+            phys_offset,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the offset between virtual addresses and their target physical
+    /// addresses.
+    pub const fn phys_offset(&self) -> usize {
+        self.phys_offset
+    }
+
+    /// Returns the target physical address of the given virtual address.
+    pub fn phys_addr(&self, vaddr: P::Addr) -> PhysAddr {
+        PhysAddr::from(vaddr.into().wrapping_sub(self.phys_offset))
+    }
+}
+
+impl<P: LinearPageTable> Clone for LinearBackend<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: LinearPageTable> Copy for LinearBackend<P> {}
+
+impl<P: LinearPageTable> MappingBackend for LinearBackend<P> {
+    type Addr = P::Addr;
+    type Flags = P::Flags;
+    type PageTable = P;
+    type Error = ();
+
+    fn map(
+        &self,
+        start: P::Addr,
+        size: usize,
+        flags: P::Flags,
+        page_table: &mut P,
+    ) -> Result<(), ()> {
+        page_table
+            .map_region(start, self.phys_addr(start), size, flags)
+            .then_some(())
+            .ok_or(())
+    }
+
+    fn unmap(&self, start: P::Addr, size: usize, page_table: &mut P) -> Result<(), ()> {
+        page_table.unmap_region(start, size).then_some(()).ok_or(())
+    }
+
+    fn protect(
+        &self,
+        start: P::Addr,
+        size: usize,
+        new_flags: P::Flags,
+        page_table: &mut P,
+    ) -> Result<(), ()> {
+        page_table
+            .protect_region(start, size, new_flags)
+            .then_some(())
+            .ok_or(())
+    }
+
+    fn mergeable(&self, other: &Self) -> bool {
+        self.phys_offset == other.phys_offset
+    }
+}
@@ -0,0 +1,5 @@
+//! Ready-made [`MappingBackend`](crate::MappingBackend) implementations.
+
+mod linear;
+
+pub use self::linear::{LinearBackend, LinearPageTable};
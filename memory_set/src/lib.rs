@@ -5,25 +5,33 @@ extern crate alloc;
 
 mod area;
 mod backend;
+pub mod backends;
 mod set;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 #[cfg(test)]
 mod tests;
 
-pub use self::area::MemoryArea;
+pub use self::area::{MemoryArea, MemoryAreaBuilder};
 pub use self::backend::MappingBackend;
-pub use self::set::MemorySet;
+pub use self::set::{MemorySet, MemorySetSnapshot};
 
 /// Error type for memory mapping operations.
+///
+/// `E` is the error type of the [`MappingBackend`], which defaults to `()`.
 #[derive(Debug, Eq, PartialEq)]
-pub enum MappingError {
+pub enum MappingError<E = ()> {
     /// Invalid parameter (e.g., `addr`, `size`, `flags`, etc.)
     InvalidParam,
     /// The given range overlaps with an existing mapping.
     AlreadyExists,
-    /// The backend page table is in a bad state.
-    BadState,
+    /// The backend page table is in a bad state, with the error returned by
+    /// the backend.
+    BadState(E),
+    /// No free memory area is available to satisfy the request.
+    NoMemory,
 }
 
 /// A [`Result`] type with [`MappingError`] as the error type.
-pub type MappingResult<T = ()> = Result<T, MappingError>;
+pub type MappingResult<T = (), E = ()> = Result<T, MappingError<E>>;
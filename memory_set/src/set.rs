@@ -8,11 +8,24 @@ use memory_addr::{AddrRange, MemoryAddr};
 use crate::{MappingBackend, MappingError, MappingResult, MemoryArea};
 
 /// A container that maintains memory mappings ([`MemoryArea`]).
-pub struct MemorySet<B: MappingBackend> {
-    areas: BTreeMap<B::Addr, MemoryArea<B>>,
+///
+/// `M` is the type of the user metadata attached to each area (see
+/// [`MemoryArea::metadata`]). It must be [`Clone`] since an area may be split
+/// into several parts, each of which gets a copy of the metadata.
+pub struct MemorySet<B: MappingBackend, M = ()> {
+    areas: BTreeMap<B::Addr, MemoryArea<B, M>>,
 }
 
-impl<B: MappingBackend> MemorySet<B> {
+/// A snapshot of the memory areas in a [`MemorySet`], taken by
+/// [`MemorySet::snapshot`] and restored by [`MemorySet::restore`].
+///
+/// It only records the areas (ranges, flags, backends and metadata), not the
+/// contents of the page table.
+pub struct MemorySetSnapshot<B: MappingBackend, M = ()> {
+    areas: BTreeMap<B::Addr, MemoryArea<B, M>>,
+}
+
+impl<B: MappingBackend, M> MemorySet<B, M> {
     /// Creates a new memory set.
     pub const fn new() -> Self {
         Self {
@@ -31,35 +44,200 @@ impl<B: MappingBackend> MemorySet<B> {
     }
 
     /// Returns the iterator over all memory areas.
-    pub fn iter(&self) -> impl Iterator<Item = &MemoryArea<B>> {
+    pub fn iter(&self) -> impl Iterator<Item = &MemoryArea<B, M>> {
         self.areas.values()
     }
 
+    /// Returns the memory area with the lowest start address, or `None` if the
+    /// memory set is empty.
+    pub fn first(&self) -> Option<&MemoryArea<B, M>> {
+        self.areas.values().next()
+    }
+
+    /// Returns the memory area with the highest start address, or `None` if
+    /// the memory set is empty.
+    pub fn last(&self) -> Option<&MemoryArea<B, M>> {
+        self.areas.values().next_back()
+    }
+
+    /// Returns the iterator over the range and flags of all memory areas, in
+    /// ascending order.
+    ///
+    /// It only yields the plain data of the areas without the backends, e.g.,
+    /// for serializing the layout of the memory set.
+    pub fn layout(&self) -> impl Iterator<Item = (AddrRange<B::Addr>, B::Flags)> + '_ {
+        self.areas
+            .values()
+            .map(|area| (area.va_range(), area.flags()))
+    }
+
+    /// Checks whether the two memory sets have the same layout, i.e., the same
+    /// sequence of ranges and flags as yielded by [`MemorySet::layout`].
+    ///
+    /// The backends and metadata of the areas are not compared.
+    pub fn layout_eq(&self, other: &MemorySet<B, M>) -> bool
+    where
+        B::Flags: PartialEq,
+    {
+        self.layout().eq(other.layout())
+    }
+
+    /// Returns the mutable iterator over all memory areas, in ascending order.
+    ///
+    /// Like [`MemorySet::find_mut`], it is intended for in-place updates that
+    /// do not change the address ranges of the areas (e.g.,
+    /// [`MemoryArea::update_flags`]). **Changing the range** of any area
+    /// through it **is not allowed**.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut MemoryArea<B, M>> {
+        self.areas.values_mut()
+    }
+
+    /// Returns references to all memory areas in ascending order of their start
+    /// addresses.
+    ///
+    /// Unlike [`MemorySet::iter`], the result supports indexing and binary
+    /// search.
+    pub fn areas(&self) -> Vec<&MemoryArea<B, M>> {
+        self.areas.values().collect()
+    }
+
+    /// Returns the iterator over all memory areas that overlap with the given
+    /// address range, in ascending order.
+    ///
+    /// This includes the area that starts before `range.start` but extends
+    /// into the range.
+    pub fn iter_overlapping(
+        &self,
+        range: AddrRange<B::Addr>,
+    ) -> impl Iterator<Item = &MemoryArea<B, M>> {
+        let first = self
+            .areas
+            .range(..range.start)
+            .last()
+            .map_or(range.start, |(&start, _)| start);
+        self.areas
+            .range(first..)
+            .map(|(_, area)| area)
+            .take_while(move |area| area.start() < range.end)
+            .filter(move |area| area.va_range().overlaps(range))
+    }
+
+    /// Returns the iterator over all maximal unmapped ranges within `limit`, in
+    /// ascending order.
+    ///
+    /// Areas that are partially outside `limit` are clipped, so every yielded
+    /// range is a non-empty sub-range of `limit`.
+    pub fn iter_free(
+        &self,
+        limit: AddrRange<B::Addr>,
+    ) -> impl Iterator<Item = AddrRange<B::Addr>> + '_ {
+        let mut cursor = limit.start;
+        self.iter_overlapping(limit)
+            .map(Some)
+            .chain(core::iter::once(None))
+            .filter_map(move |area| {
+                let gap_end = area.map_or(limit.end, |a| a.start().min(limit.end));
+                let gap = (cursor < gap_end).then(|| AddrRange::new(cursor, gap_end));
+                if let Some(area) = area {
+                    cursor = cursor.max(area.end());
+                }
+                gap
+            })
+    }
+
     /// Returns whether the given address range overlaps with any existing area.
     pub fn overlaps(&self, range: AddrRange<B::Addr>) -> bool {
+        self.find_overlap(range).is_some()
+    }
+
+    /// Returns the first (lowest) existing area that overlaps with the given
+    /// address range, or `None` if there is no such area.
+    pub fn find_overlap(&self, range: AddrRange<B::Addr>) -> Option<&MemoryArea<B, M>> {
         if let Some((_, before)) = self.areas.range(..range.start).last() {
             if before.va_range().overlaps(range) {
-                return true;
+                return Some(before);
             }
         }
         if let Some((_, after)) = self.areas.range(range.start..).next() {
             if after.va_range().overlaps(range) {
-                return true;
+                return Some(after);
             }
         }
-        false
+        None
+    }
+
+    /// Returns the total size of the mapped memory within the given address
+    /// range.
+    ///
+    /// Only the parts of the areas that overlap with `range` are counted.
+    pub fn mapped_size_in(&self, range: AddrRange<B::Addr>) -> usize {
+        self.iter_overlapping(range)
+            .filter_map(|area| area.clip(range))
+            .map(|r| r.size())
+            .sum()
     }
 
     /// Finds the memory area that contains the given address.
-    pub fn find(&self, addr: B::Addr) -> Option<&MemoryArea<B>> {
+    pub fn find(&self, addr: B::Addr) -> Option<&MemoryArea<B, M>> {
         let candidate = self.areas.range(..=addr).last().map(|(_, a)| a);
         candidate.filter(|a| a.va_range().contains(addr))
     }
 
+    /// Finds the memory area that starts exactly at the given address.
+    ///
+    /// Unlike [`MemorySet::find`], an address inside an area (but not at its
+    /// start) does not match. This is a direct lookup by key, so it is
+    /// cheaper than [`MemorySet::find`].
+    pub fn find_exact(&self, start: B::Addr) -> Option<&MemoryArea<B, M>> {
+        self.areas.get(&start)
+    }
+
+    /// Finds the memory area that contains the given address, and returns a
+    /// mutable reference to it.
+    ///
+    /// It is intended for in-place updates that do not change the address
+    /// range of the area, e.g., [`MemoryArea::update_flags`] or
+    /// [`MemoryArea::metadata_mut`]. The areas are keyed by their start
+    /// addresses and must not overlap, so **changing the range** through this
+    /// reference (e.g., by [`MemoryArea::extend_left`] or
+    /// [`MemoryArea::extend_right`]) **is not allowed**. Use
+    /// [`MemorySet::resize`] or [`MemorySet::remap_area`] instead.
+    pub fn find_mut(&mut self, addr: B::Addr) -> Option<&mut MemoryArea<B, M>> {
+        let candidate = self.areas.range_mut(..=addr).last().map(|(_, a)| a);
+        candidate.filter(|a| a.va_range().contains(addr))
+    }
+
+    /// Returns the flags of the memory area that contains the given address.
+    ///
+    /// Returns `None` if the address is not mapped.
+    pub fn flags_at(&self, addr: B::Addr) -> Option<B::Flags> {
+        self.find(addr).map(|area| area.flags())
+    }
+
+    /// Returns the memory areas immediately before and after the gap that
+    /// contains the given address.
+    ///
+    /// Returns `(None, None)` if the address is inside an existing area. If
+    /// there is no area before or after the gap, the corresponding side is
+    /// `None`.
+    #[allow(clippy::type_complexity)]
+    pub fn gap_neighbors(
+        &self,
+        addr: B::Addr,
+    ) -> (Option<&MemoryArea<B, M>>, Option<&MemoryArea<B, M>>) {
+        let before = self.areas.range(..=addr).last().map(|(_, a)| a);
+        if before.is_some_and(|a| a.va_range().contains(addr)) {
+            return (None, None);
+        }
+        let after = self.areas.range(addr..).next().map(|(_, a)| a);
+        (before, after)
+    }
+
     /// Finds a free area that can accommodate the given size.
     ///
     /// The search starts from the given `hint` address, and the area should be
-    /// within the given `limit` range.
+    /// within the given `limit` range. The start address of the area is
+    /// aligned up to `align`, which must be a power of two.
     ///
     /// Returns the start address of the free area. Returns `None` if no such
     /// area is found.
@@ -68,23 +246,91 @@ impl<B: MappingBackend> MemorySet<B> {
         hint: B::Addr,
         size: usize,
         limit: AddrRange<B::Addr>,
+        align: usize,
     ) -> Option<B::Addr> {
-        // brute force: try each area's end address as the start.
+        // Whether [start, start + size) fits before `gap_end` and the limit.
+        let fits = |start: B::Addr, gap_end: B::Addr| {
+            start
+                .checked_add(size)
+                .is_some_and(|end| end <= gap_end && end <= limit.end)
+        };
+
         let mut last_end = hint.max(limit.start);
-        for (&addr, area) in self.areas.iter() {
-            if last_end.checked_add(size).is_some_and(|end| end <= addr) {
-                return Some(last_end);
+        // Skip the areas before `last_end`. Only the last of them may extend
+        // beyond it.
+        if let Some((_, before)) = self.areas.range(..last_end).next_back() {
+            last_end = last_end.max(before.end());
+        }
+
+        // Try each area's end address as the start.
+        for (&addr, area) in self.areas.range(last_end..) {
+            if addr >= limit.end {
+                break;
             }
-            last_end = area.end();
+            // Rounding up can only overflow at the top of the address space,
+            // where nothing can fit anymore.
+            let start = last_end.checked_next_multiple_of(align)?;
+            if fits(start, addr) {
+                return Some(start);
+            }
+            last_end = last_end.max(area.end());
         }
-        if last_end
-            .checked_add(size)
-            .is_some_and(|end| end <= limit.end)
-        {
-            Some(last_end)
-        } else {
-            None
+        let start = last_end.checked_next_multiple_of(align)?;
+        fits(start, limit.end).then_some(start)
+    }
+
+    /// Finds a free area that can accommodate the given size, searching from
+    /// the top of the given `limit` range downwards.
+    ///
+    /// Returns the highest start address of such a free area that is aligned
+    /// to `align`, which must be a power of two. Returns `None` if no such area
+    /// is found.
+    pub fn find_free_area_top_down(
+        &self,
+        size: usize,
+        align: usize,
+        limit: AddrRange<B::Addr>,
+    ) -> Option<B::Addr> {
+        // Returns the highest aligned start address in the gap
+        // [gap_start, gap_end) that fits `size`, if any.
+        let fit = |gap_start: B::Addr, gap_end: B::Addr| {
+            gap_end
+                .checked_sub(size)
+                .map(|start| start.align_down(align))
+                .filter(|&start| start >= gap_start)
+        };
+
+        let mut gap_end = limit.end;
+        for area in self.areas.range(..limit.end).rev().map(|(_, a)| a) {
+            let gap_start = area.end().max(limit.start);
+            if gap_start < gap_end {
+                if let Some(start) = fit(gap_start, gap_end) {
+                    return Some(start);
+                }
+            }
+            gap_end = gap_end.min(area.start());
+            if gap_end <= limit.start {
+                return None;
+            }
         }
+        fit(limit.start, gap_end)
+    }
+
+    /// Returns a clone of the backend of the first area that overlaps with the
+    /// given range, which is used to call [`MappingBackend::finish_batch`].
+    fn batch_backend(&self, range: AddrRange<B::Addr>) -> Option<B> {
+        self.iter_overlapping(range)
+            .next()
+            .map(|area| area.backend().clone())
+    }
+
+    /// Returns whether each boundary of `range` that falls inside `area` is
+    /// aligned to the [page size](MappingBackend::page_size) of its backend,
+    /// i.e., whether `area` can be cut at the boundaries of `range`.
+    fn is_cut_aligned(area: &MemoryArea<B, M>, range: AddrRange<B::Addr>) -> bool {
+        let page_size = area.backend().page_size();
+        (range.start <= area.start() || range.start.is_aligned(page_size))
+            && (range.end >= area.end() || range.end.is_aligned(page_size))
     }
 
     /// Add a new memory mapping.
@@ -95,63 +341,536 @@ impl<B: MappingBackend> MemorySet<B> {
     /// determined by the `unmap_overlap` parameter. If it is `true`, the
     /// overlapped regions will be unmapped first. Otherwise, it returns an
     /// error.
+    ///
+    /// [`MappingBackend::finish_batch`] of the new area's backend is called
+    /// once with the range of the new area after the page table is changed.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if the area is empty, or its
+    /// start address or size is not aligned to the
+    /// [page size](MappingBackend::page_size) of its backend. With
+    /// `unmap_overlap`, the boundaries of the area that fall inside an
+    /// existing area must also be aligned to the page size of that area's
+    /// backend.
+    ///
+    /// If the backend fails to map the new area, [`MappingError::BadState`] is
+    /// returned and the new area is not added. Without `unmap_overlap`, the
+    /// memory set is left unchanged. With `unmap_overlap`, the overlapped
+    /// regions have already been unmapped and are **not** restored, since
+    /// their contents may be gone (e.g., the frames have been freed), so the
+    /// memory set is left with a hole at the range of the new area.
+    ///
+    /// In either case, the memory set stays consistent on its own, but any
+    /// entries that the backend has partially mapped for the new area are not
+    /// unmapped by the memory set. Cleaning them up is the responsibility of
+    /// the backend's [`map`](MappingBackend::map).
     pub fn map(
         &mut self,
-        area: MemoryArea<B>,
+        area: MemoryArea<B, M>,
         page_table: &mut B::PageTable,
         unmap_overlap: bool,
-    ) -> MappingResult {
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
         if area.va_range().is_empty() {
             return Err(MappingError::InvalidParam);
         }
+        let page_size = area.backend().page_size();
+        if !area.start().is_aligned(page_size) || !area.size().is_aligned(page_size) {
+            return Err(MappingError::InvalidParam);
+        }
 
         if self.overlaps(area.va_range()) {
             if unmap_overlap {
-                self.unmap(area.start(), area.size(), page_table)?;
+                let range = area.va_range();
+                if !self
+                    .iter_overlapping(range)
+                    .all(|other| Self::is_cut_aligned(other, range))
+                {
+                    return Err(MappingError::InvalidParam);
+                }
+                if let Err(e) = self.unmap_range(area.va_range(), page_table) {
+                    area.backend().finish_batch(area.va_range(), page_table);
+                    return Err(e);
+                }
             } else {
                 return Err(MappingError::AlreadyExists);
             }
         }
 
-        area.map_area(page_table)?;
+        let result = area.map_area(page_table);
+        area.backend().finish_batch(area.va_range(), page_table);
+        result?;
         assert!(self.areas.insert(area.start(), area).is_none());
         Ok(())
     }
 
+    /// Adds multiple memory mappings at once, e.g., the segments of an ELF
+    /// file.
+    ///
+    /// All areas are validated before the page table is touched: they must be
+    /// valid for [`MemorySet::map`] and must not overlap with each other
+    /// ([`MappingError::InvalidParam`] otherwise). Overlaps with the existing
+    /// areas are handled according to `unmap_overlap`, the same as
+    /// [`MemorySet::map`].
+    ///
+    /// If mapping any area fails, the areas of this batch that have already
+    /// been mapped are unmapped again before the error is returned, so either
+    /// all or none of the areas are added. As with [`MemorySet::map`], regions
+    /// unmapped due to `unmap_overlap` are not restored.
+    ///
+    /// If unmapping some of them fails as well, the rollback still goes on
+    /// with the remaining areas, and the first error of the rollback is
+    /// returned instead. The areas of this batch that could not be unmapped
+    /// are left in the memory set.
+    pub fn map_many(
+        &mut self,
+        areas: impl IntoIterator<Item = MemoryArea<B, M>>,
+        page_table: &mut B::PageTable,
+        unmap_overlap: bool,
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        let mut areas = areas.into_iter().collect::<Vec<_>>();
+        areas.sort_unstable_by_key(|area| area.start());
+        for area in &areas {
+            let page_size = area.backend().page_size();
+            if area.va_range().is_empty()
+                || !area.start().is_aligned(page_size)
+                || !area.size().is_aligned(page_size)
+            {
+                return Err(MappingError::InvalidParam);
+            }
+            if !unmap_overlap && self.overlaps(area.va_range()) {
+                return Err(MappingError::AlreadyExists);
+            }
+            if !self
+                .iter_overlapping(area.va_range())
+                .all(|other| Self::is_cut_aligned(other, area.va_range()))
+            {
+                return Err(MappingError::InvalidParam);
+            }
+        }
+        if areas.windows(2).any(|w| w[0].end() > w[1].start()) {
+            return Err(MappingError::InvalidParam);
+        }
+
+        let mut mapped: Vec<AddrRange<B::Addr>> = Vec::with_capacity(areas.len());
+        for area in areas {
+            let range = area.va_range();
+            if let Err(e) = self.map(area, page_table, unmap_overlap) {
+                // Roll back the areas mapped by this batch, keeping on after a
+                // failure so that as many of them as possible are removed.
+                let mut rollback_err = None;
+                for range in mapped {
+                    if let Err(err) = self.unmap(range.start, range.size(), page_table) {
+                        rollback_err.get_or_insert(err);
+                    }
+                }
+                return Err(rollback_err.unwrap_or(e));
+            }
+            mapped.push(range);
+        }
+        Ok(())
+    }
+
+    /// Maps a new memory area, unmapping any overlapped regions first, and
+    /// returns the ranges that were displaced by the new area.
+    ///
+    /// It is similar to [`MemorySet::map`] with `unmap_overlap` set to
+    /// `true` (e.g., for `MAP_FIXED`), but lets the caller know which parts of
+    /// the existing areas were removed. The returned ranges are in ascending
+    /// order, one for each overlapped area, and are empty if the range was
+    /// free.
+    pub fn map_replace(
+        &mut self,
+        area: MemoryArea<B, M>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<Vec<AddrRange<B::Addr>>, B::Error>
+    where
+        M: Clone,
+    {
+        let range = area.va_range();
+        let displaced = self
+            .iter_overlapping(range)
+            .filter_map(|area| area.clip(range))
+            .collect();
+        self.map(area, page_table, true)?;
+        Ok(displaced)
+    }
+
+    /// Finds a free area within `limit` and maps a new memory area there.
+    ///
+    /// The new area has the given `size`, `flags` and `backend`, and its start
+    /// address is aligned to `align`, which must be a power of two. The search
+    /// is done by [`MemorySet::find_free_area`] from the start of `limit`.
+    ///
+    /// Returns the start address of the new area, or
+    /// [`MappingError::NoMemory`] if no free area is large enough.
+    pub fn map_alloc(
+        &mut self,
+        size: usize,
+        align: usize,
+        flags: B::Flags,
+        backend: B,
+        limit: AddrRange<B::Addr>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<B::Addr, B::Error>
+    where
+        M: Clone + Default,
+    {
+        self.map_alloc_with(size, align, flags, limit, page_table, |_| backend)
+    }
+
+    /// Finds a free area within `limit` and maps a new memory area there, with
+    /// the backend built by `make_backend` from the chosen start address.
+    ///
+    /// It is useful when the backend depends on the address of the area, e.g.,
+    /// the offset of a linear mapping. See [`MemorySet::map_alloc`] for
+    /// details.
+    pub fn map_alloc_with(
+        &mut self,
+        size: usize,
+        align: usize,
+        flags: B::Flags,
+        limit: AddrRange<B::Addr>,
+        page_table: &mut B::PageTable,
+        make_backend: impl FnOnce(B::Addr) -> B,
+    ) -> MappingResult<B::Addr, B::Error>
+    where
+        M: Clone + Default,
+    {
+        let start = self
+            .find_free_area(limit.start, size, limit, align)
+            .ok_or(MappingError::NoMemory)?;
+        self.map(
+            MemoryArea::with_metadata(start, size, flags, make_backend(start), M::default()),
+            page_table,
+            false,
+        )?;
+        Ok(start)
+    }
+
+    /// Maps the given area if its range is free, or returns the existing area
+    /// if an identical one (with the same range and flags) is already mapped.
+    ///
+    /// Returns [`MappingError::AlreadyExists`] if the range overlaps with any
+    /// other existing area.
+    pub fn map_or_get(
+        &mut self,
+        area: MemoryArea<B, M>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<&MemoryArea<B, M>, B::Error>
+    where
+        M: Clone,
+        B::Flags: PartialEq,
+    {
+        let start = area.start();
+        let identical = self
+            .areas
+            .get(&start)
+            .is_some_and(|a| a.va_range() == area.va_range() && a.flags() == area.flags());
+        if !identical {
+            self.map(area, page_table, false)?;
+        }
+        Ok(&self.areas[&start])
+    }
+
+    /// Changes the size of the memory area that starts exactly at `start`.
+    ///
+    /// If `new_size` is smaller than the current size, the tail of the area is
+    /// unmapped. If it is larger, the area is extended to the right and the
+    /// added part is mapped with the same flags, which requires the space to be
+    /// free.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if no area starts at `start`, or
+    /// `new_size` is 0 (use [`MemorySet::unmap`] instead) or not aligned to
+    /// the [page size](MappingBackend::page_size) of the area's backend, or
+    /// the new end address overflows. Returns [`MappingError::AlreadyExists`]
+    /// if the grown area would overlap with the next area.
+    ///
+    /// [`MappingBackend::finish_batch`] of the area's backend is called once
+    /// with the added or removed part afterwards.
+    pub fn resize(
+        &mut self,
+        start: B::Addr,
+        new_size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        let area = self.areas.get(&start).ok_or(MappingError::InvalidParam)?;
+        if new_size == 0 || !new_size.is_aligned(area.backend().page_size()) {
+            return Err(MappingError::InvalidParam);
+        }
+        let old_size = area.size();
+        if new_size == old_size {
+            return Ok(());
+        }
+        let new_end = start
+            .checked_add(new_size)
+            .ok_or(MappingError::InvalidParam)?;
+        if new_size > old_size {
+            if let Some((&next_start, _)) = self.areas.range(start..).nth(1) {
+                if next_start < new_end {
+                    return Err(MappingError::AlreadyExists);
+                }
+            }
+        }
+
+        let area = self.areas.get_mut(&start).unwrap();
+        let old_end = area.end();
+        let (result, affected) = if new_size > old_size {
+            (
+                area.extend_right(new_size - old_size, page_table),
+                AddrRange::new(old_end, new_end),
+            )
+        } else {
+            (
+                area.shrink_right(new_size, page_table),
+                AddrRange::new(new_end, old_end),
+            )
+        };
+        area.backend().finish_batch(affected, page_table);
+        result
+    }
+
+    /// Splits the memory area that contains `at` into two areas at `at`,
+    /// without changing the page table.
+    ///
+    /// Returns `Ok(true)` if the area is split, or `Ok(false)` if `at` is
+    /// already the start of an area. Returns [`MappingError::InvalidParam`] if
+    /// `at` is not mapped, or not aligned to the
+    /// [page size](MappingBackend::page_size) of the area's backend.
+    pub fn split_at(&mut self, at: B::Addr) -> MappingResult<bool, B::Error>
+    where
+        M: Clone,
+    {
+        let area = self.find_mut(at).ok_or(MappingError::InvalidParam)?;
+        if area.start() == at {
+            return Ok(false);
+        }
+        if !at.is_aligned(area.backend().page_size()) {
+            return Err(MappingError::InvalidParam);
+        }
+        let right_part = area.split(at).unwrap();
+        self.areas.insert(at, right_part);
+        Ok(true)
+    }
+
+    /// Moves the memory area that starts exactly at `old_start` to
+    /// `new_start`, using [`MappingBackend::remap`].
+    ///
+    /// The new range may overlap with the old one, but not with any other
+    /// area.
+    ///
+    /// Returns [`MappingError::InvalidParam`] if no area starts at
+    /// `old_start`, `new_start` is not aligned to the
+    /// [page size](MappingBackend::page_size) of the area's backend, or the
+    /// new end address overflows. Returns
+    /// [`MappingError::AlreadyExists`] if the new range overlaps with another
+    /// area. If the backend fails to move the mappings,
+    /// [`MappingError::BadState`] is returned and the area stays at its old
+    /// position.
+    ///
+    /// [`MappingBackend::finish_batch`] of the area's backend is called once
+    /// afterwards, with the smallest range covering both the old and the new
+    /// ranges.
+    pub fn remap_area(
+        &mut self,
+        old_start: B::Addr,
+        new_start: B::Addr,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        let area = self
+            .areas
+            .get(&old_start)
+            .ok_or(MappingError::InvalidParam)?;
+        if !new_start.is_aligned(area.backend().page_size()) {
+            return Err(MappingError::InvalidParam);
+        }
+        let size = area.size();
+        let new_range =
+            AddrRange::try_from_start_size(new_start, size).ok_or(MappingError::InvalidParam)?;
+        if self
+            .iter_overlapping(new_range)
+            .any(|area| area.start() != old_start)
+        {
+            return Err(MappingError::AlreadyExists);
+        }
+
+        let mut area = self.areas.remove(&old_start).unwrap();
+        let affected = AddrRange::new(old_start.min(new_start), area.end().max(new_range.end));
+        let result = area.remap_area(new_start, page_table);
+        area.backend().finish_batch(affected, page_table);
+        self.areas.insert(area.start(), area);
+        result
+    }
+
     /// Remove memory mappings within the given address range.
     ///
     /// All memory areas that are fully contained in the range will be removed
     /// directly. If the area intersects with the boundary, it will be shrinked.
     /// If the unmapped range is in the middle of an existing area, it will be
     /// split into two areas.
+    ///
+    /// If any area is affected, [`MappingBackend::finish_batch`] of the first
+    /// affected area's backend is called once with the whole range afterwards.
+    ///
+    /// Returns [`MappingError::InvalidParam`] without changing anything if the
+    /// end address overflows, or a boundary of the range falls inside an area
+    /// but is not aligned to the [page size](MappingBackend::page_size) of its
+    /// backend.
     pub fn unmap(
         &mut self,
         start: B::Addr,
         size: usize,
         page_table: &mut B::PageTable,
-    ) -> MappingResult {
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        self.unmap_count(start, size, page_table).map(|_| ())
+    }
+
+    /// Remove memory mappings within the given address range, and returns the
+    /// number of memory areas affected.
+    ///
+    /// An area is counted once if it is removed, shrunk, or split during the
+    /// operation. Returns 0 if nothing is unmapped. See [`MemorySet::unmap`]
+    /// for details.
+    pub fn unmap_count(
+        &mut self,
+        start: B::Addr,
+        size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<usize, B::Error>
+    where
+        M: Clone,
+    {
         let range =
             AddrRange::try_from_start_size(start, size).ok_or(MappingError::InvalidParam)?;
         if range.is_empty() {
-            return Ok(());
+            return Ok(0);
         }
+        if !self
+            .iter_overlapping(range)
+            .all(|area| Self::is_cut_aligned(area, range))
+        {
+            return Err(MappingError::InvalidParam);
+        }
+        let backend = self.batch_backend(range);
+        let result = self.unmap_range(range, page_table);
+        if let Some(backend) = backend {
+            backend.finish_batch(range, page_table);
+        }
+        result
+    }
 
-        let end = range.end;
+    /// Remove memory mappings within the given address range, but only from
+    /// the areas that match the predicate `pred`.
+    ///
+    /// The parts of the matching areas inside `range` are unmapped the same
+    /// way as [`MemorySet::unmap`] (i.e., the areas may be shrunk or split),
+    /// while the non-matching areas are left intact even if they overlap with
+    /// the range.
+    ///
+    /// If any area is unmapped, [`MappingBackend::finish_batch`] of the first
+    /// unmapped area's backend is called once with the whole range afterwards.
+    ///
+    /// Returns [`MappingError::InvalidParam`] without changing anything if a
+    /// boundary of the range falls inside a matching area but is not aligned
+    /// to the [page size](MappingBackend::page_size) of its backend.
+    pub fn unmap_where(
+        &mut self,
+        range: AddrRange<B::Addr>,
+        pred: impl Fn(&MemoryArea<B, M>) -> bool,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        let mut backend = None;
+        let mut sub_ranges = Vec::new();
+        for area in self.iter_overlapping(range).filter(|area| pred(area)) {
+            if !Self::is_cut_aligned(area, range) {
+                return Err(MappingError::InvalidParam);
+            }
+            if backend.is_none() {
+                backend = Some(area.backend().clone());
+            }
+            sub_ranges.extend(area.clip(range));
+        }
+
+        // Each sub-range lies within a single matching area, so no other area
+        // is affected.
+        let result = sub_ranges
+            .into_iter()
+            .try_for_each(|sub_range| self.unmap_range(sub_range, page_table).map(|_| ()));
+        if let Some(backend) = backend {
+            backend.finish_batch(range, page_table);
+        }
+        result
+    }
+
+    /// Remove memory mappings within the given address range, and returns the
+    /// ranges of the memory areas that were entirely removed, in ascending
+    /// order.
+    ///
+    /// Only areas fully contained in the range are reported, e.g., for freeing
+    /// their backing frames at a higher layer. Areas that are merely shrunk or
+    /// split at the boundaries of the range are **not** reported. See
+    /// [`MemorySet::unmap`] for details.
+    pub fn unmap_collect(
+        &mut self,
+        start: B::Addr,
+        size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<Vec<AddrRange<B::Addr>>, B::Error>
+    where
+        M: Clone,
+    {
+        let range =
+            AddrRange::try_from_start_size(start, size).ok_or(MappingError::InvalidParam)?;
+        let removed = self
+            .iter_overlapping(range)
+            .map(|area| area.va_range())
+            .filter(|area_range| area_range.contained_in(range))
+            .collect();
+        self.unmap_count(start, size, page_table)?;
+        Ok(removed)
+    }
+
+    /// Unmaps the given non-empty range without calling
+    /// [`MappingBackend::finish_batch`].
+    fn unmap_range(
+        &mut self,
+        range: AddrRange<B::Addr>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<usize, B::Error>
+    where
+        M: Clone,
+    {
+        let (start, end) = (range.start, range.end);
+        let mut count = 0;
 
         // Unmap entire areas that are contained by the range.
+        let mut result = Ok(());
         self.areas.retain(|_, area| {
-            if area.va_range().contained_in(range) {
-                area.unmap_area(page_table).unwrap();
-                false
+            if result.is_ok() && area.va_range().contained_in(range) {
+                result = area.unmap_area(page_table);
+                count += 1;
+                result.is_err()
             } else {
                 true
             }
         });
+        result?;
 
         // Shrink right if the area intersects with the left boundary.
         if let Some((&before_start, before)) = self.areas.range_mut(..start).last() {
             let before_end = before.end();
             if before_end > start {
+                count += 1;
                 if before_end <= end {
                     // the unmapped area is at the end of `before`.
                     before.shrink_right(start.sub_addr(before_start), page_table)?;
@@ -170,6 +889,7 @@ impl<B: MappingBackend> MemorySet<B> {
             let after_end = after.end();
             if after_start < end {
                 // the unmapped area is at the start of `after`.
+                count += 1;
                 let mut new_area = self.areas.remove(&after_start).unwrap();
                 new_area.shrink_left(after_end.sub_addr(end), page_table)?;
                 assert_eq!(new_area.start().into(), Into::<usize>::into(end));
@@ -177,16 +897,242 @@ impl<B: MappingBackend> MemorySet<B> {
             }
         }
 
+        Ok(count)
+    }
+
+    /// Remove memory mappings within the given address range, and returns
+    /// whether the memory set became empty after the operation.
+    ///
+    /// See [`MemorySet::unmap`] for details.
+    pub fn unmap_is_empty_after(
+        &mut self,
+        start: B::Addr,
+        size: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<bool, B::Error>
+    where
+        M: Clone,
+    {
+        self.unmap(start, size, page_table)?;
+        Ok(self.is_empty())
+    }
+
+    /// Duplicates the memory set into a new page table.
+    ///
+    /// Each memory area is copied and mapped into `new_page_table` eagerly,
+    /// as a `fork()`-style address space duplication. Whether the physical
+    /// frames are shared or copied is up to the backend's `map` operation.
+    ///
+    /// On failure, the areas already mapped into `new_page_table` are left as
+    /// is.
+    pub fn try_clone_into(&self, new_page_table: &mut B::PageTable) -> MappingResult<Self, B::Error>
+    where
+        M: Clone,
+    {
+        let mut new_set = Self::new();
+        for area in self.areas.values() {
+            new_set.map(area.clone(), new_page_table, false)?;
+        }
+        Ok(new_set)
+    }
+
+    /// Takes a snapshot of the memory areas, which can be restored later by
+    /// [`MemorySet::restore`] to roll back speculative changes.
+    ///
+    /// The page table is not recorded.
+    pub fn snapshot(&self) -> MemorySetSnapshot<B, M>
+    where
+        M: Clone,
+    {
+        MemorySetSnapshot {
+            areas: self.areas.clone(),
+        }
+    }
+
+    /// Restores the memory areas from a snapshot taken by
+    /// [`MemorySet::snapshot`], and reconciles the page table to match.
+    ///
+    /// Areas with the same range and flags as in the snapshot are kept as is
+    /// in the page table. Other current areas are unmapped first, and then the
+    /// missing areas of the snapshot are mapped again. Unlike
+    /// [`MemorySet::try_clone_into`], which builds a fresh page table, only
+    /// the differences are applied to the existing one. Note that the contents
+    /// of the re-mapped areas are up to the backend's `map` operation.
+    ///
+    /// If the backend fails, it stops there and returns
+    /// [`MappingError::BadState`], with the memory set still matching the page
+    /// table.
+    pub fn restore(
+        &mut self,
+        snapshot: MemorySetSnapshot<B, M>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error>
+    where
+        B::Flags: PartialEq,
+    {
+        let stale = self
+            .areas
+            .values()
+            .filter(|area| {
+                !snapshot.areas.get(&area.start()).is_some_and(|saved| {
+                    saved.va_range() == area.va_range() && saved.flags() == area.flags()
+                })
+            })
+            .map(|area| area.start())
+            .collect::<Vec<_>>();
+        for start in stale {
+            let area = &self.areas[&start];
+            let result = area.unmap_area(page_table);
+            area.backend().finish_batch(area.va_range(), page_table);
+            result?;
+            self.areas.remove(&start);
+        }
+
+        for (start, area) in snapshot.areas {
+            if !self.areas.contains_key(&start) {
+                let result = area.map_area(page_table);
+                area.backend().finish_batch(area.va_range(), page_table);
+                result?;
+            }
+            // Also restores the backend and metadata of the kept areas.
+            self.areas.insert(start, area);
+        }
         Ok(())
     }
 
+    /// Copies the layout of the memory set into a new memory set with a
+    /// different backend type, mapping every area into `new_page_table`.
+    ///
+    /// The backend of each new area is built by `make` from the original
+    /// area, while the range, flags and metadata are copied. Unlike
+    /// [`MemorySet::try_clone_into`], it allows migrating to another page
+    /// table implementation.
+    ///
+    /// On failure, the areas already mapped into `new_page_table` are left as
+    /// is.
+    pub fn map_layout_into<B2>(
+        &self,
+        new_page_table: &mut B2::PageTable,
+        make: impl Fn(&MemoryArea<B, M>) -> B2,
+    ) -> MappingResult<MemorySet<B2, M>, B2::Error>
+    where
+        M: Clone,
+        B2: MappingBackend<Addr = B::Addr, Flags = B::Flags>,
+    {
+        let mut new_set = MemorySet::new();
+        for area in self.areas.values() {
+            let new_area = MemoryArea::with_metadata(
+                area.start(),
+                area.size(),
+                area.flags(),
+                make(area),
+                area.metadata().clone(),
+            );
+            new_set.map(new_area, new_page_table, false)?;
+        }
+        Ok(new_set)
+    }
+
+    /// Merges adjacent memory areas with the same flags into one.
+    ///
+    /// Two areas are merged if the end of the first is the start of the
+    /// second, their flags and metadata are equal, and their backends are
+    /// [`mergeable`](MappingBackend::mergeable). The page table is not
+    /// touched.
+    pub fn merge_adjacent(&mut self)
+    where
+        B::Flags: PartialEq,
+        M: PartialEq,
+    {
+        let mut areas = core::mem::take(&mut self.areas).into_values();
+        let Some(mut cur) = areas.next() else {
+            return;
+        };
+        for next in areas {
+            if cur.end() == next.start()
+                && cur.flags() == next.flags()
+                && cur.backend().mergeable(next.backend())
+                && cur.metadata() == next.metadata()
+            {
+                cur.set_end(next.end());
+            } else {
+                self.areas.insert(cur.start(), cur);
+                cur = next;
+            }
+        }
+        self.areas.insert(cur.start(), cur);
+    }
+
+    /// Unmaps and removes every memory area for which `keep` returns `false`.
+    ///
+    /// Areas are visited in ascending order. If unmapping an area fails, it
+    /// stops there and returns [`MappingError::BadState`]: the failed area and
+    /// all areas after it are left intact in the memory set.
+    ///
+    /// If any area is unmapped, [`MappingBackend::finish_batch`] of the first
+    /// unmapped area's backend is called once afterwards, with the range from
+    /// the start of the first unmapped area to the end of the last one.
+    pub fn retain_areas(
+        &mut self,
+        keep: impl Fn(&MemoryArea<B, M>) -> bool,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        let mut batch: Option<(B, AddrRange<B::Addr>)> = None;
+        let mut result = Ok(());
+        self.areas.retain(|_, area| {
+            if result.is_err() || keep(area) {
+                return true;
+            }
+            match &mut batch {
+                Some((_, affected)) => affected.end = area.end(),
+                None => batch = Some((area.backend().clone(), area.va_range())),
+            }
+            result = area.unmap_area(page_table);
+            result.is_err()
+        });
+        if let Some((backend, affected)) = batch {
+            backend.finish_batch(affected, page_table);
+        }
+        result
+    }
+
     /// Remove all memory areas and the underlying mappings.
-    pub fn clear(&mut self, page_table: &mut B::PageTable) -> MappingResult {
-        for (_, area) in self.areas.iter() {
-            area.unmap_area(page_table)?;
+    ///
+    /// See [`MemorySet::clear_count`] for the behavior on failure.
+    pub fn clear(&mut self, page_table: &mut B::PageTable) -> MappingResult<(), B::Error> {
+        self.clear_count(page_table).map(|_| ())
+    }
+
+    /// Remove all memory areas and the underlying mappings, returning the
+    /// number of areas removed.
+    ///
+    /// Areas are unmapped in ascending order. If unmapping an area fails, it
+    /// stops there and returns [`MappingError::BadState`]: the areas already
+    /// unmapped are removed, while the failed one and all areas after it are
+    /// left intact in the memory set.
+    ///
+    /// If the memory set is not empty, [`MappingBackend::finish_batch`] of the
+    /// first area's backend is called once afterwards, with the range from
+    /// the start of the first area to the end of the last one.
+    pub fn clear_count(&mut self, page_table: &mut B::PageTable) -> MappingResult<usize, B::Error> {
+        let (Some(first), Some(last)) = (self.first(), self.last()) else {
+            return Ok(0);
+        };
+        let affected = AddrRange::new(first.start(), last.end());
+        let backend = first.backend().clone();
+
+        let mut count = 0;
+        let mut result = Ok(());
+        while let Some(entry) = self.areas.first_entry() {
+            result = entry.get().unmap_area(page_table);
+            if result.is_err() {
+                break;
+            }
+            entry.remove();
+            count += 1;
         }
-        self.areas.clear();
-        Ok(())
+        backend.finish_batch(affected, page_table);
+        result.map(|_| count)
     }
 
     /// Change the flags of memory mappings within the given address range.
@@ -198,14 +1144,86 @@ impl<B: MappingBackend> MemorySet<B> {
     /// Memory areas will be skipped according to `update_flags`. Memory areas
     /// that are fully contained in the range or contains the range or
     /// intersects with the boundary will be handled similarly to `munmap`.
+    ///
+    /// If the flags of any area are changed, [`MappingBackend::finish_batch`]
+    /// of the first area overlapping with the range is called once with the
+    /// whole range afterwards.
+    ///
+    /// Returns [`MappingError::InvalidParam`] without changing anything if the
+    /// end address overflows, or a boundary of the range falls inside an area
+    /// but is not aligned to the [page size](MappingBackend::page_size) of its
+    /// backend.
+    ///
+    /// If the backend fails to change the flags of an area, it stops there and
+    /// returns [`MappingError::BadState`]. The failed area keeps its old flags,
+    /// while the areas before it have been updated.
     pub fn protect(
         &mut self,
         start: B::Addr,
         size: usize,
         update_flags: impl Fn(B::Flags) -> Option<B::Flags>,
         page_table: &mut B::PageTable,
-    ) -> MappingResult {
-        let end = start.checked_add(size).ok_or(MappingError::InvalidParam)?;
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        self.protect_reporting(start, size, update_flags, page_table)
+            .map(|_| ())
+    }
+
+    /// Change the flags of memory mappings within the given address range,
+    /// and returns whether the flags of any area were updated.
+    ///
+    /// Returns `false` if no area overlaps with the range, or `update_flags`
+    /// returns [`None`] for all the overlapping areas. See
+    /// [`MemorySet::protect`] for details.
+    pub fn protect_reporting(
+        &mut self,
+        start: B::Addr,
+        size: usize,
+        update_flags: impl Fn(B::Flags) -> Option<B::Flags>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<bool, B::Error>
+    where
+        M: Clone,
+    {
+        let range =
+            AddrRange::try_from_start_size(start, size).ok_or(MappingError::InvalidParam)?;
+        if range.is_empty() {
+            // Nothing to protect. Also avoids creating an empty middle part
+            // when splitting an area.
+            return Ok(false);
+        }
+        if !self
+            .iter_overlapping(range)
+            .all(|area| Self::is_cut_aligned(area, range))
+        {
+            return Err(MappingError::InvalidParam);
+        }
+        let backend = self.batch_backend(range);
+        let result = self.protect_range(range, update_flags, page_table);
+        // On failure, the areas before the failed one may have been updated.
+        if let Some(backend) = backend.filter(|_| !matches!(result, Ok(false))) {
+            backend.finish_batch(range, page_table);
+        }
+        result
+    }
+
+    /// Changes the flags within the given non-empty range without calling
+    /// [`MappingBackend::finish_batch`], and returns whether any area was
+    /// updated.
+    fn protect_range(
+        &mut self,
+        range: AddrRange<B::Addr>,
+        update_flags: impl Fn(B::Flags) -> Option<B::Flags>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<bool, B::Error>
+    where
+        M: Clone,
+    {
+        let (start, end, size) = (range.start, range.end, range.size());
+        let mut changed = false;
+        let mut result = Ok(());
         let mut to_insert = Vec::new();
         for (&area_start, area) in self.areas.iter_mut() {
             let area_end = area.end();
@@ -218,50 +1236,240 @@ impl<B: MappingBackend> MemorySet<B> {
                 } else if area_end <= start {
                     //          [ prot ]
                     // [ area ]
-                    // Do nothing
-                } else if area_start >= start && area_end <= end {
+                    continue;
+                }
+                changed = true;
+                // The split parts are always inserted, even if the backend
+                // fails, so that no mapped region is lost from the set.
+                result = if area_start >= start && area_end <= end {
                     // [   prot   ]
                     //   [ area ]
-                    area.protect_area(new_flags, page_table)?;
-                    area.set_flags(new_flags);
+                    area.update_flags(new_flags, page_table)
                 } else if area_start < start && area_end > end {
                     //        [ prot ]
                     // [ left | area | right ]
                     let right_part = area.split(end).unwrap();
                     area.set_end(start);
 
-                    let mut middle_part =
-                        MemoryArea::new(start, size, area.flags(), area.backend().clone());
-                    middle_part.protect_area(new_flags, page_table)?;
-                    middle_part.set_flags(new_flags);
+                    let mut middle_part = MemoryArea::with_metadata(
+                        start,
+                        size,
+                        area.flags(),
+                        area.backend().clone(),
+                        area.metadata().clone(),
+                    );
+                    let result = middle_part.update_flags(new_flags, page_table);
 
                     to_insert.push((right_part.start(), right_part));
                     to_insert.push((middle_part.start(), middle_part));
+                    result
                 } else if area_end > end {
                     // [    prot ]
                     //   [  area | right ]
                     let right_part = area.split(end).unwrap();
-                    area.protect_area(new_flags, page_table)?;
-                    area.set_flags(new_flags);
-
                     to_insert.push((right_part.start(), right_part));
+                    area.update_flags(new_flags, page_table)
                 } else {
                     //        [ prot    ]
                     // [ left |  area ]
                     let mut right_part = area.split(start).unwrap();
-                    right_part.protect_area(new_flags, page_table)?;
-                    right_part.set_flags(new_flags);
-
+                    let result = right_part.update_flags(new_flags, page_table);
                     to_insert.push((right_part.start(), right_part));
+                    result
+                };
+                if result.is_err() {
+                    break;
                 }
             }
         }
         self.areas.extend(to_insert);
+        result.map(|_| changed)
+    }
+
+    /// Takes a snapshot of the flags of memory mappings within the given
+    /// address range.
+    ///
+    /// Returns a list of sub-ranges of `range` covered by memory areas, along
+    /// with their flags, in ascending order. Unmapped parts of `range` are not
+    /// included. The snapshot can be restored later by
+    /// [`MemorySet::restore_flags`].
+    pub fn snapshot_flags(&self, range: AddrRange<B::Addr>) -> Vec<(AddrRange<B::Addr>, B::Flags)> {
+        let mut snapshot = Vec::new();
+        if range.is_empty() {
+            return snapshot;
+        }
+        for area in self.iter_overlapping(range) {
+            if let Some(sub_range) = area.clip(range) {
+                snapshot.push((sub_range, area.flags()));
+            }
+        }
+        snapshot
+    }
+
+    /// Restores the flags of memory mappings from a snapshot taken by
+    /// [`MemorySet::snapshot_flags`].
+    ///
+    /// Each saved flag is re-applied to its sub-range via
+    /// [`MemorySet::protect`].
+    pub fn restore_flags(
+        &mut self,
+        snapshot: &[(AddrRange<B::Addr>, B::Flags)],
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        for &(range, flags) in snapshot {
+            self.protect(range.start, range.size(), |_| Some(flags), page_table)?;
+        }
+        Ok(())
+    }
+
+    /// Change the flags of all memory mappings in the memory set.
+    ///
+    /// `update_flags` is the same as in [`MemorySet::protect`]. Since whole
+    /// areas are affected, no area is split.
+    ///
+    /// If the flags of any area are changed, [`MappingBackend::finish_batch`]
+    /// of the first changed area's backend is called once afterwards, with
+    /// the range from the start of the first changed area to the end of the
+    /// last one.
+    pub fn protect_all(
+        &mut self,
+        update_flags: impl Fn(B::Flags) -> Option<B::Flags>,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error> {
+        let mut batch: Option<(B, AddrRange<B::Addr>)> = None;
+        let mut result = Ok(());
+        for area in self.areas.values_mut() {
+            if let Some(new_flags) = update_flags(area.flags()) {
+                match &mut batch {
+                    Some((_, affected)) => affected.end = area.end(),
+                    None => batch = Some((area.backend().clone(), area.va_range())),
+                }
+                result = area.update_flags(new_flags, page_table);
+                if result.is_err() {
+                    break;
+                }
+            }
+        }
+        if let Some((backend, affected)) = batch {
+            backend.finish_batch(affected, page_table);
+        }
+        result
+    }
+
+    /// Change the flags of memory mappings within the given address range,
+    /// with a cap on the number of new areas created by splitting.
+    ///
+    /// This is the same as [`MemorySet::protect`], except that it returns
+    /// [`MappingError::InvalidParam`] without changing anything if the
+    /// operation would create more than `max_splits` new areas.
+    pub fn protect_bounded(
+        &mut self,
+        start: B::Addr,
+        size: usize,
+        update_flags: impl Fn(B::Flags) -> Option<B::Flags>,
+        max_splits: usize,
+        page_table: &mut B::PageTable,
+    ) -> MappingResult<(), B::Error>
+    where
+        M: Clone,
+    {
+        let range =
+            AddrRange::try_from_start_size(start, size).ok_or(MappingError::InvalidParam)?;
+        if range.is_empty() {
+            return Ok(());
+        }
+        let mut splits = 0;
+        for area in self.iter_overlapping(range) {
+            if update_flags(area.flags()).is_none() {
+                continue;
+            }
+            // Each boundary of the range that falls inside the area splits off
+            // a new area.
+            if area.start() < range.start {
+                splits += 1;
+            }
+            if area.end() > range.end {
+                splits += 1;
+            }
+        }
+        if splits > max_splits {
+            return Err(MappingError::InvalidParam);
+        }
+        self.protect(start, size, update_flags, page_table)
+    }
+}
+
+impl<B: MappingBackend, M> MemorySet<B, M> {
+    /// Checks the internal invariants of the memory set, and panics if any of
+    /// them is violated:
+    ///
+    /// - Each area is keyed by its start address.
+    /// - No area is empty.
+    /// - Areas are sorted and do not overlap with each other.
+    #[cfg(test)]
+    pub(crate) fn check_invariants(&self) {
+        let mut last_end: Option<B::Addr> = None;
+        for (&key, area) in self.areas.iter() {
+            let (start, end) = (area.start().into(), area.end().into());
+            assert_eq!(
+                key.into(),
+                start,
+                "area {start:#x}..{end:#x} has a wrong key"
+            );
+            assert!(start < end, "area {start:#x}..{end:#x} is empty");
+            if let Some(last_end) = last_end {
+                let last_end = last_end.into();
+                assert!(
+                    last_end <= start,
+                    "area {start:#x}..{end:#x} overlaps with the previous one ending at {last_end:#x}"
+                );
+            }
+            last_end = Some(area.end());
+        }
+    }
+}
+
+impl<B: MappingBackend, M> MemorySet<B, M> {
+    /// Returns a [`Display`](fmt::Display)able table of all memory areas, for
+    /// debugging.
+    ///
+    /// Each area is printed on its own line in ascending order, e.g.,
+    /// `VA:0x1000-VA:0x3000 size=0x2000 flags=1`.
+    pub fn dump(&self) -> impl fmt::Display + '_
+    where
+        B::Addr: fmt::Debug,
+        B::Flags: fmt::Debug,
+    {
+        Dump(self)
+    }
+}
+
+struct Dump<'a, B: MappingBackend, M>(&'a MemorySet<B, M>);
+
+impl<B: MappingBackend, M> fmt::Display for Dump<'_, B, M>
+where
+    B::Addr: fmt::Debug,
+    B::Flags: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for area in self.0.areas.values() {
+            writeln!(
+                f,
+                "{:?}-{:?} size={:#x} flags={:?}",
+                area.start(),
+                area.end(),
+                area.size(),
+                area.flags()
+            )?;
+        }
         Ok(())
     }
 }
 
-impl<B: MappingBackend> fmt::Debug for MemorySet<B>
+impl<B: MappingBackend, M> fmt::Debug for MemorySet<B, M>
 where
     B::Addr: fmt::Debug,
     B::Flags: fmt::Debug,
@@ -0,0 +1,111 @@
+//! Utilities for testing code built on top of [`MemorySet`](crate::MemorySet).
+//!
+//! Available with the `testing` feature.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use memory_addr::MemoryAddr;
+
+use crate::MappingBackend;
+
+/// The kind of a backend operation recorded by [`RecordingBackend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// [`MappingBackend::map`].
+    Map,
+    /// [`MappingBackend::unmap`].
+    Unmap,
+    /// [`MappingBackend::protect`].
+    Protect,
+}
+
+/// The "page table" of [`RecordingBackend`], which is a log of
+/// `(op, start, size, flags)` of every backend operation, in call order.
+///
+/// `flags` is `None` for [`Op::Unmap`].
+pub type RecordLog<A, F> = Vec<(Op, A, usize, Option<F>)>;
+
+/// A [`MappingBackend`] that only records the operations in its page table
+/// ([`RecordLog`]), and always succeeds.
+///
+/// It is useful to assert on the sequence of backend calls produced by the
+/// higher-level code.
+///
+/// # Example
+///
+/// ```
+/// use memory_addr::{va, VirtAddr};
+/// use memory_set::testing::{Op, RecordingBackend};
+/// use memory_set::{MemoryArea, MemorySet};
+///
+/// let backend = RecordingBackend::<VirtAddr, u8>::new(0x1000);
+/// let mut set = MemorySet::new();
+/// let mut log = Vec::new();
+///
+/// set.map(MemoryArea::new(va!(0x1000), 0x3000, 1, backend), &mut log, false)
+///     .unwrap();
+/// set.unmap(va!(0x2000), 0x1000, &mut log).unwrap();
+/// assert_eq!(
+///     log,
+///     [
+///         (Op::Map, va!(0x1000), 0x3000, Some(1)),
+///         (Op::Unmap, va!(0x2000), 0x1000, None),
+///     ]
+/// );
+/// ```
+pub struct RecordingBackend<A, F> {
+    page_size: usize,
+    _phantom: PhantomData<fn(A, F)>,
+}
+
+impl<A, F> RecordingBackend<A, F> {
+    /// Creates a new recording backend with the given page size, which must
+    /// be a power of two.
+    pub const fn new(page_size: usize) -> Self {
+        Self {
+            page_size,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A, F> Clone for RecordingBackend<A, F> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, F> Copy for RecordingBackend<A, F> {}
+
+impl<A: MemoryAddr, F: Copy> MappingBackend for RecordingBackend<A, F> {
+    type Addr = A;
+    type Flags = F;
+    type PageTable = RecordLog<A, F>;
+    type Error = ();
+
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn map(&self, start: A, size: usize, flags: F, log: &mut RecordLog<A, F>) -> Result<(), ()> {
+        log.push((Op::Map, start, size, Some(flags)));
+        Ok(())
+    }
+
+    fn unmap(&self, start: A, size: usize, log: &mut RecordLog<A, F>) -> Result<(), ()> {
+        log.push((Op::Unmap, start, size, None));
+        Ok(())
+    }
+
+    fn protect(
+        &self,
+        start: A,
+        size: usize,
+        new_flags: F,
+        log: &mut RecordLog<A, F>,
+    ) -> Result<(), ()> {
+        log.push((Op::Protect, start, size, Some(new_flags)));
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
-use memory_addr::{MemoryAddr, VirtAddr};
+use memory_addr::{va_range, MemoryAddr, PhysAddr, VirtAddr, VirtAddrRange};
 
+use crate::backends::{LinearBackend, LinearPageTable};
 use crate::{MappingBackend, MappingError, MemoryArea, MemorySet};
 
 const MAX_ADDR: usize = 0x10000;
@@ -16,25 +17,32 @@ impl MappingBackend for MockBackend {
     type Addr = VirtAddr;
     type Flags = MockFlags;
     type PageTable = MockPageTable;
+    type Error = ();
 
-    fn map(&self, start: VirtAddr, size: usize, flags: MockFlags, pt: &mut MockPageTable) -> bool {
+    fn map(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MockFlags,
+        pt: &mut MockPageTable,
+    ) -> Result<(), ()> {
         for entry in pt.iter_mut().skip(start.as_usize()).take(size) {
             if *entry != 0 {
-                return false;
+                return Err(());
             }
             *entry = flags;
         }
-        true
+        Ok(())
     }
 
-    fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> bool {
+    fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
         for entry in pt.iter_mut().skip(start.as_usize()).take(size) {
             if *entry == 0 {
-                return false;
+                return Err(());
             }
             *entry = 0;
         }
-        true
+        Ok(())
     }
 
     fn protect(
@@ -43,14 +51,58 @@ impl MappingBackend for MockBackend {
         size: usize,
         new_flags: MockFlags,
         pt: &mut MockPageTable,
-    ) -> bool {
+    ) -> Result<(), ()> {
         for entry in pt.iter_mut().skip(start.as_usize()).take(size) {
             if *entry == 0 {
-                return false;
+                return Err(());
             }
             *entry = new_flags;
         }
-        true
+        Ok(())
+    }
+}
+
+const HUGE_PAGE_SIZE: usize = 0x1000;
+
+/// A mock backend that maps memory in huge pages of [`HUGE_PAGE_SIZE`] bytes.
+#[derive(Clone)]
+struct HugeBackend;
+
+impl MappingBackend for HugeBackend {
+    type Addr = VirtAddr;
+    type Flags = MockFlags;
+    type PageTable = MockPageTable;
+    type Error = ();
+
+    fn page_size(&self) -> usize {
+        HUGE_PAGE_SIZE
+    }
+
+    fn map(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        flags: MockFlags,
+        pt: &mut MockPageTable,
+    ) -> Result<(), ()> {
+        assert!(start.is_aligned(HUGE_PAGE_SIZE) && size.is_aligned(HUGE_PAGE_SIZE));
+        MockBackend.map(start, size, flags, pt)
+    }
+
+    fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
+        assert!(start.is_aligned(HUGE_PAGE_SIZE) && size.is_aligned(HUGE_PAGE_SIZE));
+        MockBackend.unmap(start, size, pt)
+    }
+
+    fn protect(
+        &self,
+        start: VirtAddr,
+        size: usize,
+        new_flags: MockFlags,
+        pt: &mut MockPageTable,
+    ) -> Result<(), ()> {
+        assert!(start.is_aligned(HUGE_PAGE_SIZE) && size.is_aligned(HUGE_PAGE_SIZE));
+        MockBackend.protect(start, size, new_flags, pt)
     }
 }
 
@@ -64,8 +116,8 @@ macro_rules! assert_err {
     ($expr: expr) => {
         assert!(($expr).is_err())
     };
-    ($expr: expr, $err: ident) => {
-        assert_eq!(($expr).err(), Some(MappingError::$err))
+    ($expr: expr, $($err: tt)+) => {
+        assert_eq!(($expr).err(), Some(MappingError::$($err)+))
     };
 }
 
@@ -75,9 +127,7 @@ fn dump_memory_set(set: &MockMemorySet) {
 
     let _lock = DUMP_LOCK.lock().unwrap();
     println!("Number of areas: {}", set.len());
-    for area in set.iter() {
-        println!("{:?}", area);
-    }
+    print!("{}", set.dump());
 }
 
 #[test]
@@ -229,6 +279,43 @@ fn test_unmap_split() {
     }
 }
 
+#[test]
+fn test_unmap_collect() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Shrinking or splitting areas removes nothing entirely.
+    assert_eq!(set.unmap_collect(0xc00.into(), 0x1800, &mut pt), Ok(vec![]));
+    assert_eq!(set.unmap_collect(0x4800.into(), 0x100, &mut pt), Ok(vec![]));
+    assert_eq!(set.len(), 9);
+
+    // [0x4900, 0x5000), [0x6000, 0x7000) and [0x8000, 0x9000) are removed,
+    // while [0x4000, 0x4800) and [0xa000, 0xb000) are shrunk.
+    let removed = set.unmap_collect(0x4400.into(), 0x6000, &mut pt).unwrap();
+    dump_memory_set(&set);
+    assert_eq!(
+        removed,
+        [
+            va_range!(0x4900..0x5000),
+            va_range!(0x6000..0x7000),
+            va_range!(0x8000..0x9000),
+        ]
+    );
+    assert_eq!(set.find(0x4000.into()).unwrap().end(), 0x4400.into());
+    assert_eq!(set.find(0xa400.into()).unwrap().start(), 0xa400.into());
+    assert!(pt[0x4400..0xa400].iter().all(|&f| f == 0));
+    set.check_invariants();
+}
+
 #[test]
 fn test_protect() {
     let mut set = MockMemorySet::new();
@@ -326,3 +413,2759 @@ fn test_protect() {
         assert_eq!(pt[addr], 0);
     }
 }
+
+#[test]
+fn test_areas() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert!(set.areas().is_empty());
+
+    // Map out of order.
+    for start in [0x4000, 0x1000, 0x8000, 0x2000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let areas = set.areas();
+    assert_eq!(areas.len(), 4);
+    assert!(areas
+        .iter()
+        .map(|a| a.va_range())
+        .eq(set.iter().map(|a| a.va_range())));
+    assert_eq!(areas[0].start(), 0x1000.into());
+    assert_eq!(areas[3].start(), 0x8000.into());
+    let pos = areas.binary_search_by_key(&0x4000.into(), |a| a.start());
+    assert_eq!(pos, Ok(2));
+}
+
+#[test]
+fn test_first_last() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert!(set.first().is_none());
+    assert!(set.last().is_none());
+
+    assert_ok!(set.map(
+        MemoryArea::new(0x4000.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    assert_eq!(set.first().unwrap().start(), 0x4000.into());
+    assert_eq!(set.last().unwrap().start(), 0x4000.into());
+
+    for start in [0x8000, 0x1000, 0x6000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(set.first().unwrap().va_range(), va_range!(0x1000..0x2000));
+    assert_eq!(set.last().unwrap().va_range(), va_range!(0x8000..0x9000));
+}
+
+#[test]
+fn test_layout() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_eq!(set.layout().count(), 0);
+
+    for (start, size, flags) in [
+        (0x4000, 0x1000, 2),
+        (0x1000, 0x2000, 1),
+        (0x8000, 0x3000, 3),
+    ] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x3000), 1),
+            (va_range!(0x4000..0x5000), 2),
+            (va_range!(0x8000..0xb000), 3),
+        ]
+    );
+}
+
+#[test]
+fn test_iter_mut() {
+    const EXEC: MockFlags = 0x4;
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    for (start, flags) in [(0x1000, 0x1 | EXEC), (0x3000, 0x3), (0x5000, 0x3 | EXEC)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    for area in set.iter_mut() {
+        let new_flags = area.flags() & !EXEC;
+        assert_ok!(area.update_flags(new_flags, &mut pt));
+    }
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x2000), 0x1),
+            (va_range!(0x3000..0x4000), 0x3),
+            (va_range!(0x5000..0x6000), 0x3),
+        ]
+    );
+    assert!(pt.iter().all(|&f| f & EXEC == 0));
+}
+
+#[test]
+fn test_find_exact() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x3000), [0x3000, 0x4000).
+    for (start, size) in [(0x1000, 0x2000), (0x3000, 0x1000)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Interior addresses are found by `find` only.
+    assert_eq!(
+        set.find(0x2000.into()).unwrap().va_range(),
+        va_range!(0x1000..0x3000)
+    );
+    assert!(set.find_exact(0x2000.into()).is_none());
+
+    // Area starts are found by both.
+    for start in [0x1000, 0x3000] {
+        let area = set.find_exact(start.into()).unwrap();
+        assert_eq!(area.start(), start.into());
+        assert_eq!(area.va_range(), set.find(start.into()).unwrap().va_range());
+    }
+
+    // The end of the last area is not mapped.
+    assert!(set.find_exact(0x4000.into()).is_none());
+    assert!(set.find_exact(0.into()).is_none());
+}
+
+#[test]
+fn test_find_mut() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x3000), [0x4000, 0x5000).
+    for (start, size) in [(0x1000, 0x2000), (0x4000, 0x1000)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let area = set.find_mut(0x2800.into()).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1000..0x3000));
+    assert_ok!(area.update_flags(3, &mut pt));
+    assert_eq!(set.flags_at(0x1000.into()), Some(3));
+    assert_eq!(set.flags_at(0x4000.into()), Some(1));
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 3));
+    assert!(pt[0x4000..0x5000].iter().all(|&f| f == 1));
+
+    assert!(set.find_mut(0x3000.into()).is_none());
+    assert!(set.find_mut(0xfff.into()).is_none());
+}
+
+#[test]
+fn test_dump() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_eq!(set.dump().to_string(), "");
+
+    // Map [0x1000, 0x3000), [0x4000, 0x5000).
+    for (start, size, flags) in [(0x1000, 0x2000, 1), (0x4000, 0x1000, 3)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(
+        set.dump().to_string(),
+        "VA:0x1000-VA:0x3000 size=0x2000 flags=1\n\
+         VA:0x4000-VA:0x5000 size=0x1000 flags=3\n"
+    );
+}
+
+#[test]
+fn test_flags_at() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 3, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    assert_eq!(set.flags_at(0x1000.into()), Some(3));
+    assert_eq!(set.flags_at(0x2fff.into()), Some(3));
+    assert_eq!(set.flags_at(0xfff.into()), None);
+    assert_eq!(set.flags_at(0x3000.into()), None);
+}
+
+#[test]
+fn test_iter_free() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // An empty set yields the whole limit.
+    let limit = va_range!(0x1000..0x8000);
+    assert_eq!(set.iter_free(limit).collect::<Vec<_>>(), [limit]);
+
+    // Map [0x1000, 0x2000), [0x4000, 0x5000), [0x7000, 0x9000).
+    for (start, size) in [(0x1000, 0x1000), (0x4000, 0x1000), (0x7000, 0x2000)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    assert_eq!(
+        set.iter_free(va_range!(0..0xa000)).collect::<Vec<_>>(),
+        [
+            va_range!(0..0x1000),
+            va_range!(0x2000..0x4000),
+            va_range!(0x5000..0x7000),
+            va_range!(0x9000..0xa000),
+        ]
+    );
+
+    // Areas partially outside the limit are clipped.
+    assert_eq!(
+        set.iter_free(va_range!(0x1800..0x7800)).collect::<Vec<_>>(),
+        [va_range!(0x2000..0x4000), va_range!(0x5000..0x7000)]
+    );
+    assert_eq!(
+        set.iter_free(va_range!(0x2800..0x3800)).collect::<Vec<_>>(),
+        [va_range!(0x2800..0x3800)]
+    );
+
+    // No free range inside an area or within an empty limit.
+    assert_eq!(set.iter_free(va_range!(0x4000..0x5000)).count(), 0);
+    assert_eq!(set.iter_free(va_range!(0x3000..0x3000)).count(), 0);
+}
+
+#[test]
+fn test_find_overlap() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x2000), [0x3000, 0x4000).
+    for start in [0x1000, 0x3000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Straddling both areas: the lower one is returned.
+    let area = set.find_overlap(va_range!(0x1800..0x3800)).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1000..0x2000));
+    // Starting in the gap.
+    let area = set.find_overlap(va_range!(0x2800..0x3800)).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x3000..0x4000));
+    // Inside an area.
+    let area = set.find_overlap(va_range!(0x3400..0x3800)).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x3000..0x4000));
+    // No overlap.
+    assert!(set.find_overlap(va_range!(0x2000..0x3000)).is_none());
+    assert!(set.find_overlap(va_range!(0x4000..0x5000)).is_none());
+}
+
+#[test]
+fn test_mapped_size_in() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    for start in [0x1000, 0x4000, 0x8000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x2000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    assert_eq!(set.mapped_size_in(va_range!(0..MAX_ADDR)), 0x6000);
+    assert_eq!(set.mapped_size_in(va_range!(0x3000..0x4000)), 0);
+    assert_eq!(set.mapped_size_in(va_range!(0x2000..0x2000)), 0);
+    assert_eq!(set.mapped_size_in(va_range!(0x1800..0x1c00)), 0x400);
+    // Partially covers the first and the last area, and fully the second.
+    assert_eq!(
+        set.mapped_size_in(va_range!(0x2800..0x9000)),
+        0x800 + 0x2000 + 0x1000
+    );
+}
+
+#[test]
+fn test_gap_neighbors() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x2000), [0x4000, 0x5000).
+    for start in [0x1000, 0x4000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // In the gap [0x2000, 0x4000).
+    let (before, after) = set.gap_neighbors(0x3000.into());
+    assert_eq!(before.unwrap().start(), 0x1000.into());
+    assert_eq!(after.unwrap().start(), 0x4000.into());
+    let (before, after) = set.gap_neighbors(0x2000.into());
+    assert_eq!(before.unwrap().start(), 0x1000.into());
+    assert_eq!(after.unwrap().start(), 0x4000.into());
+
+    // Before the first area and after the last area.
+    let (before, after) = set.gap_neighbors(0x800.into());
+    assert!(before.is_none());
+    assert_eq!(after.unwrap().start(), 0x1000.into());
+    let (before, after) = set.gap_neighbors(0x5000.into());
+    assert_eq!(before.unwrap().start(), 0x4000.into());
+    assert!(after.is_none());
+
+    // Inside an area.
+    let (before, after) = set.gap_neighbors(0x4800.into());
+    assert!(before.is_none() && after.is_none());
+}
+
+#[test]
+fn test_map_or_get() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // The range is free, so it is mapped.
+    let area = set
+        .map_or_get(
+            MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+            &mut pt,
+        )
+        .unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1000..0x3000));
+    assert_eq!(set.len(), 1);
+    assert_eq!(pt[0x1000], 1);
+
+    // An identical area exists, so it is returned without mapping again.
+    let area = set
+        .map_or_get(
+            MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+            &mut pt,
+        )
+        .unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1000..0x3000));
+    assert_eq!(area.flags(), 1);
+    assert_eq!(set.len(), 1);
+
+    // Conflicting areas: different flags, different range, or overlapping.
+    assert_err!(
+        set.map_or_get(
+            MemoryArea::new(0x1000.into(), 0x2000, 2, MockBackend),
+            &mut pt
+        ),
+        AlreadyExists
+    );
+    assert_err!(
+        set.map_or_get(
+            MemoryArea::new(0x1000.into(), 0x1000, 1, MockBackend),
+            &mut pt
+        ),
+        AlreadyExists
+    );
+    assert_err!(
+        set.map_or_get(
+            MemoryArea::new(0x2000.into(), 0x2000, 1, MockBackend),
+            &mut pt
+        ),
+        AlreadyExists
+    );
+    assert_eq!(set.len(), 1);
+    assert_eq!(pt[0x1000], 1);
+}
+
+#[test]
+fn test_map_failure() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    // A page table entry not tracked by the memory set makes `map` fail.
+    pt[0x5000] = 9;
+
+    // Without `unmap_overlap`, the memory set is unchanged.
+    let before = set.dump().to_string();
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x4000.into(), 0x2000, 2, MockBackend),
+            &mut pt,
+            false,
+        ),
+        BadState(())
+    );
+    assert_eq!(set.dump().to_string(), before);
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 1));
+    // The mock backend does not clean up the entries it has mapped.
+    assert!(pt[0x4000..0x5000].iter().all(|&f| f == 2));
+
+    // With `unmap_overlap`, the overlapped part is unmapped and not restored.
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x2000.into(), 0x4000, 2, MockBackend),
+            &mut pt,
+            true,
+        ),
+        BadState(())
+    );
+    assert_eq!(set.len(), 1);
+    assert_eq!(
+        set.iter().next().unwrap().va_range(),
+        va_range!(0x1000..0x2000)
+    );
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 1));
+    assert!(pt[0x2000..0x5000].iter().all(|&f| f == 2));
+    assert_eq!(pt[0x5000], 9);
+    assert!(pt[0x5001..0x6000].iter().all(|&f| f == 0));
+    set.check_invariants();
+}
+
+#[test]
+fn test_map_many() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    let segments = |flags| {
+        [(0x4000, 0x1000), (0x1000, 0x2000), (0x6000, 0x2000)]
+            .map(|(start, size)| MemoryArea::new(VirtAddr::from(start), size, flags, MockBackend))
+    };
+
+    assert_ok!(set.map_many(segments(1), &mut pt, false));
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x3000), 1),
+            (va_range!(0x4000..0x5000), 1),
+            (va_range!(0x6000..0x8000), 1),
+        ]
+    );
+    assert_err!(set.map_many(segments(2), &mut pt, false), AlreadyExists);
+    assert_ok!(set.clear(&mut pt));
+
+    // Areas within the batch overlap with each other.
+    assert_err!(
+        set.map_many(
+            [
+                MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+                MemoryArea::new(0x2000.into(), 0x2000, 1, MockBackend),
+            ],
+            &mut pt,
+            false,
+        ),
+        InvalidParam
+    );
+    assert!(set.is_empty());
+
+    // The second area fails to map, so the first one is unmapped again.
+    pt[0x4800] = 9;
+    assert_err!(set.map_many(segments(1), &mut pt, false), BadState(()));
+    assert!(set.is_empty());
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 0));
+    assert!(pt[0x6000..0x8000].iter().all(|&f| f == 0));
+}
+
+#[test]
+fn test_map_many_rollback_failure() {
+    /// A backend that can never unmap.
+    #[derive(Clone)]
+    struct StickyBackend;
+
+    impl MappingBackend for StickyBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = &'static str;
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), &'static str> {
+            MockBackend.map(start, size, flags, pt).map_err(|_| "map")
+        }
+
+        fn unmap(&self, _: VirtAddr, _: usize, _: &mut MockPageTable) -> Result<(), &'static str> {
+            Err("unmap")
+        }
+
+        fn protect(
+            &self,
+            _: VirtAddr,
+            _: usize,
+            _: MockFlags,
+            _: &mut MockPageTable,
+        ) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+
+    let mut set = MemorySet::<StickyBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    pt[0x4800] = 9;
+
+    // The rollback error is reported, and the area that could not be unmapped
+    // stays in the memory set.
+    assert_err!(
+        set.map_many(
+            [
+                MemoryArea::new(0x1000.into(), 0x2000, 1, StickyBackend),
+                MemoryArea::new(0x4000.into(), 0x1000, 1, StickyBackend),
+            ],
+            &mut pt,
+            false,
+        ),
+        BadState("unmap")
+    );
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [(va_range!(0x1000..0x3000), 1)]
+    );
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 1));
+    set.check_invariants();
+}
+
+#[test]
+fn test_map_many_rollback_partial_failure() {
+    /// A backend that cannot unmap anything starting at `0x3000`.
+    #[derive(Clone)]
+    struct PickyBackend;
+
+    impl MappingBackend for PickyBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = &'static str;
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), &'static str> {
+            MockBackend.map(start, size, flags, pt).map_err(|_| "map")
+        }
+
+        fn unmap(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            pt: &mut MockPageTable,
+        ) -> Result<(), &'static str> {
+            if start == 0x3000.into() {
+                return Err("unmap");
+            }
+            MockBackend.unmap(start, size, pt).map_err(|_| "unmap")
+        }
+
+        fn protect(
+            &self,
+            _: VirtAddr,
+            _: usize,
+            _: MockFlags,
+            _: &mut MockPageTable,
+        ) -> Result<(), &'static str> {
+            Ok(())
+        }
+    }
+
+    let mut set = MemorySet::<PickyBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    pt[0x7800] = 9;
+
+    // The rollback goes on after the failure in the middle, so only the area
+    // that could not be unmapped stays in the memory set.
+    assert_err!(
+        set.map_many(
+            [
+                MemoryArea::new(0x1000.into(), 0x1000, 1, PickyBackend),
+                MemoryArea::new(0x3000.into(), 0x1000, 1, PickyBackend),
+                MemoryArea::new(0x5000.into(), 0x1000, 1, PickyBackend),
+                MemoryArea::new(0x7000.into(), 0x1000, 1, PickyBackend),
+            ],
+            &mut pt,
+            false,
+        ),
+        BadState("unmap")
+    );
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [(va_range!(0x3000..0x4000), 1)]
+    );
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 0));
+    assert!(pt[0x3000..0x4000].iter().all(|&f| f == 1));
+    assert!(pt[0x5000..0x6000].iter().all(|&f| f == 0));
+    set.check_invariants();
+}
+
+#[test]
+fn test_map_replace() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    assert_ok!(set.map(
+        MemoryArea::new(0x4000.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    // A free range displaces nothing.
+    let displaced = set
+        .map_replace(
+            MemoryArea::new(0x8000.into(), 0x1000, 3, MockBackend),
+            &mut pt,
+        )
+        .unwrap();
+    assert!(displaced.is_empty());
+
+    // Overwrite the tail of the first area and the whole second area.
+    let displaced = set
+        .map_replace(
+            MemoryArea::new(0x2000.into(), 0x4000, 2, MockBackend),
+            &mut pt,
+        )
+        .unwrap();
+    dump_memory_set(&set);
+    assert_eq!(
+        displaced,
+        [va_range!(0x2000..0x3000), va_range!(0x4000..0x5000)]
+    );
+    assert_eq!(set.len(), 3);
+    assert_eq!(
+        set.find(0x1000.into()).unwrap().va_range(),
+        va_range!(0x1000..0x2000)
+    );
+    assert_eq!(
+        set.find(0x2000.into()).unwrap().va_range(),
+        va_range!(0x2000..0x6000)
+    );
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 1));
+    assert!(pt[0x2000..0x6000].iter().all(|&f| f == 2));
+}
+
+#[test]
+fn test_protect_reporting() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    // `update_flags` skips all areas.
+    assert_eq!(
+        set.protect_reporting(0x1000.into(), 0x2000, |_| None, &mut pt),
+        Ok(false)
+    );
+    // No area in the range.
+    assert_eq!(
+        set.protect_reporting(0x4000.into(), 0x1000, |_| Some(2), &mut pt),
+        Ok(false)
+    );
+    assert_eq!(set.len(), 1);
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 1));
+
+    assert_eq!(
+        set.protect_reporting(0x2000.into(), 0x2000, |_| Some(2), &mut pt),
+        Ok(true)
+    );
+    assert_eq!(set.len(), 2);
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 1));
+    assert!(pt[0x2000..0x3000].iter().all(|&f| f == 2));
+}
+
+#[test]
+fn test_protect_all() {
+    const EXEC: MockFlags = 0x4;
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ... with flags
+    // 0x7 and 0x3 alternately.
+    for (i, start) in (0..MAX_ADDR).step_by(0x2000).enumerate() {
+        let flags = if i % 2 == 0 { 0x7 } else { 0x3 };
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Clear the executable bit.
+    assert_ok!(set.protect_all(
+        |flags| (flags & EXEC != 0).then_some(flags & !EXEC),
+        &mut pt
+    ));
+    assert_eq!(set.len(), 8);
+    for area in set.iter() {
+        assert_eq!(area.flags(), 0x3);
+        assert!(pt[area.start().as_usize()..area.end().as_usize()]
+            .iter()
+            .all(|&f| f == 0x3));
+    }
+}
+
+#[test]
+fn test_protect_bounded() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    let update_flags = |new_flags: MockFlags| {
+        move |old_flags: MockFlags| -> Option<MockFlags> {
+            if old_flags == new_flags {
+                None
+            } else {
+                Some(new_flags)
+            }
+        }
+    };
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(set.len(), 8);
+
+    // Protect [0x800, 0x4800) would split off 2 new areas.
+    assert_err!(
+        set.protect_bounded(0x800.into(), 0x4000, update_flags(2), 1, &mut pt),
+        InvalidParam
+    );
+    // Nothing is changed.
+    assert_eq!(set.len(), 8);
+    for area in set.iter() {
+        assert_eq!(area.flags(), 1);
+        assert!(pt[area.start().as_usize()..area.end().as_usize()]
+            .iter()
+            .all(|&f| f == 1));
+    }
+
+    // Skipped areas are not counted.
+    assert_ok!(set.protect_bounded(0x800.into(), 0x4000, update_flags(1), 0, &mut pt));
+    assert_eq!(set.len(), 8);
+
+    assert_ok!(set.protect_bounded(0x800.into(), 0x4000, update_flags(2), 2, &mut pt));
+    dump_memory_set(&set);
+    assert_eq!(set.len(), 10);
+    assert_eq!(pt[0x7ff], 1);
+    assert_eq!(pt[0x800], 2);
+    assert_eq!(pt[0x2000], 2);
+    assert_eq!(pt[0x47ff], 2);
+    assert_eq!(pt[0x4800], 1);
+
+    // Protect a range in the middle of an area would split off 2 new areas.
+    assert_err!(
+        set.protect_bounded(0x6400.into(), 0x400, update_flags(2), 1, &mut pt),
+        InvalidParam
+    );
+    assert_ok!(set.protect_bounded(0x6400.into(), 0x400, update_flags(2), 2, &mut pt));
+    assert_eq!(set.len(), 12);
+
+    // An empty range does nothing, the same as `protect`.
+    assert_ok!(set.protect_bounded(0xa400.into(), 0, update_flags(2), 0, &mut pt));
+    assert_eq!(set.len(), 12);
+    assert_eq!(pt[0xa400], 1);
+}
+
+#[test]
+fn test_snapshot_restore_flags() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x2000) with flags 1, [0x2000, 0x3000) with flags 2, and
+    // [0x4000, 0x5000) with flags 3.
+    for (start, flags) in [(0x1000, 1), (0x2000, 2), (0x4000, 3)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let snapshot = set.snapshot_flags(va_range!(0x1800..0x4800));
+    assert_eq!(
+        snapshot,
+        [
+            (va_range!(0x1800..0x2000), 1),
+            (va_range!(0x2000..0x3000), 2),
+            (va_range!(0x4000..0x4800), 3),
+        ]
+    );
+
+    // Make the whole range writable.
+    assert_ok!(set.protect(0x1800.into(), 0x3000, |_| Some(7), &mut pt));
+    assert_eq!(pt[0x1800], 7);
+    assert_eq!(pt[0x2800], 7);
+    assert_eq!(pt[0x4000], 7);
+
+    // Restore the original flags.
+    assert_ok!(set.restore_flags(&snapshot, &mut pt));
+    dump_memory_set(&set);
+    for area in set.iter() {
+        let range = area.start().as_usize()..area.end().as_usize();
+        for (addr, &flags) in range.clone().zip(&pt[range]) {
+            let expected = match addr {
+                0x1000..0x2000 => 1,
+                0x2000..0x3000 => 2,
+                _ => 3,
+            };
+            assert_eq!(area.flags(), expected);
+            assert_eq!(flags, expected);
+        }
+    }
+}
+
+#[test]
+fn test_unmap_where() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    // Map [0x1000, 0x3000) with flags 1, [0x3000, 0x5000) with flags 2,
+    // [0x5000, 0x7000) with flags 1, and [0x8000, 0x9000) with flags 2.
+    for (start, size, flags) in [
+        (0x1000, 0x2000, 1),
+        (0x3000, 0x2000, 2),
+        (0x5000, 0x2000, 1),
+        (0x8000, 0x1000, 2),
+    ] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Only the areas with flags 1 are unmapped: the first one is shrunk and
+    // the third one is split.
+    assert_ok!(set.unmap_where(va_range!(0x2000..0x6000), |area| area.flags() == 1, &mut pt));
+    dump_memory_set(&set);
+    assert_eq!(
+        set.iter().map(|a| a.va_range()).collect::<Vec<_>>(),
+        [
+            va_range!(0x1000..0x2000),
+            va_range!(0x3000..0x5000),
+            va_range!(0x6000..0x7000),
+            va_range!(0x8000..0x9000),
+        ]
+    );
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 1));
+    assert!(pt[0x2000..0x3000].iter().all(|&f| f == 0));
+    assert!(pt[0x3000..0x5000].iter().all(|&f| f == 2));
+    assert!(pt[0x5000..0x6000].iter().all(|&f| f == 0));
+    assert!(pt[0x6000..0x7000].iter().all(|&f| f == 1));
+    set.check_invariants();
+
+    // Nothing matches.
+    assert_ok!(set.unmap_where(va_range!(0..0x10000), |_| false, &mut pt));
+    assert_eq!(set.len(), 4);
+
+    // Remove all areas with flags 2.
+    assert_ok!(set.unmap_where(va_range!(0..0x10000), |area| area.flags() == 2, &mut pt));
+    assert_eq!(set.len(), 2);
+    assert!(pt[0x3000..0x5000].iter().all(|&f| f == 0));
+    assert!(pt[0x8000..0x9000].iter().all(|&f| f == 0));
+}
+
+#[test]
+fn test_unmap_is_empty_after() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x2000), [0x3000, 0x4000).
+    for start in [0x1000, 0x3000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    assert_eq!(
+        set.unmap_is_empty_after(0x1000.into(), 0x1000, &mut pt),
+        Ok(false)
+    );
+    assert_eq!(
+        set.unmap_is_empty_after(0x3000.into(), 0x800, &mut pt),
+        Ok(false)
+    );
+    // Unmap the last area.
+    assert_eq!(
+        set.unmap_is_empty_after(0x3800.into(), 0x800, &mut pt),
+        Ok(true)
+    );
+    assert!(set.is_empty());
+    // Unmapping from an empty set leaves it empty.
+    assert_eq!(
+        set.unmap_is_empty_after(0.into(), MAX_ADDR, &mut pt),
+        Ok(true)
+    );
+}
+
+/// A mock page table for [`LinearBackend`], which records the flags and the
+/// target physical address of each byte.
+struct MockLinearPageTable(Vec<(MockFlags, usize)>);
+
+impl LinearPageTable for MockLinearPageTable {
+    type Addr = VirtAddr;
+    type Flags = MockFlags;
+
+    fn map_region(
+        &mut self,
+        vaddr: VirtAddr,
+        paddr: PhysAddr,
+        size: usize,
+        flags: MockFlags,
+    ) -> bool {
+        let entries = self.0.iter_mut().skip(vaddr.as_usize()).take(size);
+        for (i, entry) in entries.enumerate() {
+            if entry.0 != 0 {
+                return false;
+            }
+            *entry = (flags, paddr.as_usize() + i);
+        }
+        true
+    }
+
+    fn unmap_region(&mut self, vaddr: VirtAddr, size: usize) -> bool {
+        for entry in self.0.iter_mut().skip(vaddr.as_usize()).take(size) {
+            if entry.0 == 0 {
+                return false;
+            }
+            *entry = (0, 0);
+        }
+        true
+    }
+
+    fn protect_region(&mut self, vaddr: VirtAddr, size: usize, new_flags: MockFlags) -> bool {
+        for entry in self.0.iter_mut().skip(vaddr.as_usize()).take(size) {
+            if entry.0 == 0 {
+                return false;
+            }
+            entry.0 = new_flags;
+        }
+        true
+    }
+}
+
+#[test]
+fn test_linear_backend_map_unmap() {
+    const PHYS_OFFSET: usize = 0x8000;
+    let backend = LinearBackend::<MockLinearPageTable>::new(PHYS_OFFSET);
+    let mut set = MemorySet::<LinearBackend<MockLinearPageTable>>::new();
+    let mut pt = MockLinearPageTable(vec![(0, 0); MAX_ADDR]);
+
+    // Map [0x8000, 0x9000), [0xa000, 0xb000), [0xc000, 0xd000), ...
+    for start in (0x8000..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, backend),
+            &mut pt,
+            false,
+        ));
+    }
+    // Map [0x9000, 0xa000), [0xb000, 0xc000), [0xd000, 0xe000), ...
+    for start in (0x9000..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 2, backend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(set.len(), 8);
+    for addr in 0x8000..MAX_ADDR {
+        assert!(pt.0[addr].0 == 1 || pt.0[addr].0 == 2);
+        assert_eq!(pt.0[addr].1, addr - PHYS_OFFSET);
+    }
+
+    // Found [0xc000, 0xd000), flags = 1.
+    let area = set.find(0xc100.into()).unwrap();
+    assert_eq!(area.start(), 0xc000.into());
+    assert_eq!(area.end(), 0xd000.into());
+    assert_eq!(area.flags(), 1);
+    assert_eq!(
+        area.backend().phys_addr(area.start()),
+        PhysAddr::from(0x4000)
+    );
+
+    // The area [0xc000, 0x10000) is already mapped, map returns an error.
+    assert_err!(
+        set.map(
+            MemoryArea::new(0xc000.into(), 0x4000, 3, backend),
+            &mut pt,
+            false
+        ),
+        AlreadyExists
+    );
+    // Unmap overlapped areas before adding the new mapping [0xc000, 0x10000).
+    assert_ok!(set.map(
+        MemoryArea::new(0xc000.into(), 0x4000, 3, backend),
+        &mut pt,
+        true
+    ));
+    assert_eq!(set.len(), 5);
+    for addr in 0xc000..MAX_ADDR {
+        assert_eq!(pt.0[addr], (3, addr - PHYS_OFFSET));
+    }
+
+    // Unmap all areas.
+    assert_ok!(set.unmap(0.into(), MAX_ADDR, &mut pt));
+    assert_eq!(set.len(), 0);
+    for addr in 0..MAX_ADDR {
+        assert_eq!(pt.0[addr], (0, 0));
+    }
+}
+
+#[test]
+fn test_try_clone_into() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    // Split some areas.
+    assert_ok!(set.protect(0x2400.into(), 0x400, |_| Some(2), &mut pt));
+    assert_ok!(set.unmap(0x4800.into(), 0x100, &mut pt));
+
+    let mut new_pt = [0; MAX_ADDR];
+    let new_set = set.try_clone_into(&mut new_pt).unwrap();
+    dump_memory_set(&new_set);
+    assert_eq!(new_set.len(), set.len());
+    for (area, new_area) in set.iter().zip(new_set.iter()) {
+        assert_eq!(area.va_range(), new_area.va_range());
+        assert_eq!(area.flags(), new_area.flags());
+    }
+    assert!(pt == new_pt);
+
+    // Cloning into a page table that is not empty fails.
+    assert_err!(set.try_clone_into(&mut new_pt), BadState(()));
+}
+
+#[test]
+fn test_layout_eq() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert!(set.layout_eq(&MockMemorySet::new()));
+
+    for (start, size, flags) in [(0x1000, 0x2000, 1), (0x4000, 0x1000, 2)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let mut new_pt = [0; MAX_ADDR];
+    let mut new_set = set.try_clone_into(&mut new_pt).unwrap();
+    assert!(set.layout_eq(&new_set));
+    assert!(new_set.layout_eq(&set));
+    assert!(!set.layout_eq(&MockMemorySet::new()));
+
+    // Different flags.
+    assert_ok!(new_set.protect(0x4000.into(), 0x1000, |_| Some(3), &mut new_pt));
+    assert!(!set.layout_eq(&new_set));
+    assert_ok!(new_set.protect(0x4000.into(), 0x1000, |_| Some(2), &mut new_pt));
+    assert!(set.layout_eq(&new_set));
+
+    // Split areas covering the same range are different.
+    assert_eq!(new_set.split_at(0x2000.into()), Ok(true));
+    assert!(!set.layout_eq(&new_set));
+
+    // Different ranges.
+    assert_ok!(new_set.unmap(0x1000.into(), 0x2000, &mut new_pt));
+    assert!(!set.layout_eq(&new_set));
+}
+
+#[test]
+fn test_snapshot_restore() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    for (start, flags) in [(0x1000, 1), (0x4000, 2), (0x8000, 3)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x2000, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    let before = set.dump().to_string();
+    let pt_before = pt;
+    let snapshot = set.snapshot();
+
+    // Speculative changes: unmap, protect and map.
+    assert_ok!(set.unmap(0x1800.into(), 0x3000, &mut pt));
+    assert_ok!(set.protect(0x8000.into(), 0x1000, |_| Some(4), &mut pt));
+    assert_ok!(set.map(
+        MemoryArea::new(0xc000.into(), 0x1000, 5, MockBackend),
+        &mut pt,
+        false,
+    ));
+    dump_memory_set(&set);
+
+    // Roll back.
+    assert_ok!(set.restore(snapshot, &mut pt));
+    dump_memory_set(&set);
+    assert_eq!(set.dump().to_string(), before);
+    assert_eq!(pt, pt_before);
+    set.check_invariants();
+}
+
+#[test]
+fn test_map_layout_into() {
+    use crate::testing::{Op, RecordingBackend};
+
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    for (start, size, flags) in [(0x1000, 0x2000, 1), (0x4000, 0x1000, 2)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let mut log = Vec::new();
+    let new_set = set
+        .map_layout_into(&mut log, |_| RecordingBackend::new(0x1000))
+        .unwrap();
+    assert!(new_set.layout().eq(set.layout()));
+    assert_eq!(
+        log,
+        [
+            (Op::Map, 0x1000.into(), 0x2000, Some(1)),
+            (Op::Map, 0x4000.into(), 0x1000, Some(2)),
+        ]
+    );
+}
+
+#[test]
+fn test_merge_adjacent() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x9000), and [0xa000, 0xb000) which is not adjacent.
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x8000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    assert_ok!(set.map(
+        MemoryArea::new(0xa000.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    // Split [0x1000, 0x9000) into several areas with the same flags.
+    for start in (0x2000..0x8000).step_by(0x2000) {
+        assert_ok!(set.protect(start.into(), 0x1000, |_| Some(2), &mut pt));
+        assert_ok!(set.protect(start.into(), 0x1000, |_| Some(1), &mut pt));
+    }
+    assert_eq!(set.len(), 8);
+
+    set.merge_adjacent();
+    dump_memory_set(&set);
+    assert_eq!(set.len(), 2);
+    let areas = set.iter().collect::<Vec<_>>();
+    assert_eq!(areas[0].va_range(), va_range!(0x1000..0x9000));
+    assert_eq!(areas[1].va_range(), va_range!(0xa000..0xb000));
+
+    // Areas with different flags are not merged.
+    assert_ok!(set.protect(0x4000.into(), 0x1000, |_| Some(2), &mut pt));
+    set.merge_adjacent();
+    assert_eq!(set.len(), 4);
+
+    // The merged area can be unmapped as a whole.
+    assert_ok!(set.unmap(0.into(), MAX_ADDR, &mut pt));
+    assert!(set.is_empty());
+    assert!(pt.iter().all(|&f| f == 0));
+}
+
+#[test]
+fn test_iter_overlapping() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    let starts = |range| {
+        set.iter_overlapping(range)
+            .map(|area| area.start().as_usize())
+            .collect::<Vec<_>>()
+    };
+    // Starts in the middle of an area, ends in the middle of another one.
+    assert_eq!(starts(va_range!(0x2800..0x6800)), [0x2000, 0x4000, 0x6000]);
+    // Starts and ends in gaps.
+    assert_eq!(starts(va_range!(0x1000..0x6000)), [0x2000, 0x4000]);
+    assert_eq!(starts(va_range!(0x1800..0x1c00)), []);
+    // Touching boundaries are not overlapping.
+    assert_eq!(starts(va_range!(0x3000..0x4000)), []);
+    assert_eq!(starts(va_range!(0x3000..0x4001)), [0x4000]);
+    assert_eq!(starts(va_range!(0x4fff..0x5000)), [0x4000]);
+    // The whole space.
+    assert_eq!(starts(va_range!(0..MAX_ADDR)).len(), 8);
+}
+
+#[test]
+fn test_unmap_count() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Nothing is unmapped.
+    assert_eq!(set.unmap_count(0x1000.into(), 0x1000, &mut pt), Ok(0));
+    assert_eq!(set.unmap_count(0x1000.into(), 0, &mut pt), Ok(0));
+
+    // Unmap [0xc00, 0x2400): both boundary areas are shrunk.
+    assert_eq!(set.unmap_count(0xc00.into(), 0x1800, &mut pt), Ok(2));
+    assert_eq!(set.len(), 8);
+
+    // Unmap [0x4800, 0x4900): the area is split.
+    assert_eq!(set.unmap_count(0x4800.into(), 0x100, &mut pt), Ok(1));
+    assert_eq!(set.len(), 9);
+
+    // Unmap [0x6000, 0xa000): two areas are fully contained.
+    assert_eq!(set.unmap_count(0x6000.into(), 0x4000, &mut pt), Ok(2));
+    assert_eq!(set.len(), 7);
+
+    // Unmap [0x2800, 0xc800): one shrunk at each side, and 3 removed.
+    assert_eq!(set.unmap_count(0x2800.into(), 0xa000, &mut pt), Ok(5));
+    assert_eq!(set.len(), 4);
+
+    // Unmap all the remaining areas.
+    assert_eq!(set.unmap_count(0.into(), MAX_ADDR, &mut pt), Ok(4));
+    assert!(set.is_empty());
+}
+
+#[test]
+fn test_retain_areas() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    for (start, flags) in [
+        (0x1000, 1),
+        (0x2000, 2),
+        (0x4000, 1),
+        (0x6000, 2),
+        (0x8000, 2),
+    ] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, flags, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Unmapping fails at 0x8000, after 0x2000 and 0x6000 have been removed.
+    pt[0x8000] = 0;
+    assert_err!(
+        set.retain_areas(|area| area.flags() != 2, &mut pt),
+        BadState(())
+    );
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x2000), 1),
+            (va_range!(0x4000..0x5000), 1),
+            (va_range!(0x8000..0x9000), 2),
+        ]
+    );
+    assert!(pt[0x2000..0x3000].iter().all(|&f| f == 0));
+    assert!(pt[0x6000..0x7000].iter().all(|&f| f == 0));
+
+    pt[0x8000] = 2;
+    assert_ok!(set.retain_areas(|area| area.flags() != 2, &mut pt));
+    assert_eq!(set.len(), 2);
+    assert!(set.iter().all(|area| area.flags() == 1));
+    assert!(pt[0x8000..0x9000].iter().all(|&f| f == 0));
+}
+
+#[test]
+fn test_clear_count() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(set.clear_count(&mut pt), Ok(8));
+    assert!(set.is_empty());
+    assert!(pt.iter().all(|&f| f == 0));
+    assert_eq!(set.clear_count(&mut pt), Ok(0));
+
+    // Make unmapping the third area fail: the first two are removed and the
+    // rest are left intact.
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    pt[0x4000] = 0;
+    assert_err!(set.clear_count(&mut pt), BadState(()));
+    assert_eq!(set.len(), 6);
+    assert_eq!(set.iter().next().unwrap().start(), 0x4000.into());
+    assert!(pt[..0x4000].iter().all(|&f| f == 0));
+    assert!(pt[0x6000..0x7000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_extend_right() {
+    let mut pt = [0; MAX_ADDR];
+    let mut area = MemoryArea::new(0x1000.into(), 0x1000, 1, MockBackend);
+    assert_ok!(area.map_area(&mut pt));
+
+    assert_ok!(area.extend_right(0x2000, &mut pt));
+    assert_eq!(area.va_range(), va_range!(0x1000..0x4000));
+    assert!(pt[0x1000..0x4000].iter().all(|&f| f == 1));
+    assert_eq!(pt[0x4000], 0);
+
+    // The backend fails if the added part is already mapped.
+    pt[0x5000] = 2;
+    assert_err!(area.extend_right(0x2000, &mut pt), BadState(()));
+    assert_eq!(area.va_range(), va_range!(0x1000..0x4000));
+
+    // The new end overflows.
+    assert_err!(area.extend_right(usize::MAX, &mut pt), InvalidParam);
+    assert_eq!(area.va_range(), va_range!(0x1000..0x4000));
+
+    // The added size must be aligned to the page size.
+    let mut area = MemoryArea::new(0x8000.into(), 0x1000, 1, HugeBackend);
+    assert_ok!(area.map_area(&mut pt));
+    assert_err!(area.extend_right(0x800, &mut pt), InvalidParam);
+    assert_eq!(area.va_range(), va_range!(0x8000..0x9000));
+    assert_ok!(area.extend_right(0x1000, &mut pt));
+    assert_eq!(area.va_range(), va_range!(0x8000..0xa000));
+    assert!(pt[0x8000..0xa000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_extend_left() {
+    let mut pt = [0; MAX_ADDR];
+    let mut area = MemoryArea::new(0x4000.into(), 0x1000, 1, MockBackend);
+    assert_ok!(area.map_area(&mut pt));
+
+    assert_ok!(area.extend_left(0x2000, &mut pt));
+    assert_eq!(area.va_range(), va_range!(0x2000..0x5000));
+    assert!(pt[0x2000..0x5000].iter().all(|&f| f == 1));
+    assert_eq!(pt[0x1fff], 0);
+
+    // The new start underflows.
+    assert_err!(area.extend_left(0x3000, &mut pt), InvalidParam);
+    assert_eq!(area.va_range(), va_range!(0x2000..0x5000));
+
+    // The backend fails if the added part is already mapped.
+    pt[0x1000] = 2;
+    assert_err!(area.extend_left(0x2000, &mut pt), BadState(()));
+    assert_eq!(area.va_range(), va_range!(0x2000..0x5000));
+
+    // Grow down to address 0 (the failed attempt left partial mappings).
+    pt[..0x2000].fill(0);
+    assert_ok!(area.extend_left(0x2000, &mut pt));
+    assert_eq!(area.va_range(), va_range!(0..0x5000));
+
+    // The added size must be aligned to the page size.
+    let mut area = MemoryArea::new(0x9000.into(), 0x1000, 1, HugeBackend);
+    assert_ok!(area.map_area(&mut pt));
+    assert_err!(area.extend_left(0x800, &mut pt), InvalidParam);
+    assert_eq!(area.va_range(), va_range!(0x9000..0xa000));
+    assert_ok!(area.extend_left(0x1000, &mut pt));
+    assert_eq!(area.va_range(), va_range!(0x8000..0xa000));
+    assert!(pt[0x8000..0xa000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_resize() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x2000), [0x4000, 0x5000).
+    for start in [0x1000, 0x4000] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Grow into the free space.
+    assert_ok!(set.resize(0x1000.into(), 0x3000, &mut pt));
+    assert_eq!(
+        set.find(0x1000.into()).unwrap().va_range(),
+        va_range!(0x1000..0x4000)
+    );
+    assert!(pt[0x1000..0x5000].iter().all(|&f| f == 1));
+
+    // Grow into the next area.
+    assert_err!(set.resize(0x1000.into(), 0x3001, &mut pt), AlreadyExists);
+    assert_eq!(
+        set.find(0x1000.into()).unwrap().va_range(),
+        va_range!(0x1000..0x4000)
+    );
+
+    // Shrink.
+    assert_ok!(set.resize(0x1000.into(), 0x800, &mut pt));
+    assert_eq!(
+        set.find(0x1000.into()).unwrap().va_range(),
+        va_range!(0x1000..0x1800)
+    );
+    assert!(pt[0x1800..0x4000].iter().all(|&f| f == 0));
+
+    // Same size.
+    assert_ok!(set.resize(0x1000.into(), 0x800, &mut pt));
+    assert_eq!(set.len(), 2);
+
+    // The last area can grow up to the end of the address space.
+    assert_ok!(set.resize(0x4000.into(), MAX_ADDR - 0x4000, &mut pt));
+    assert_eq!(pt[MAX_ADDR - 1], 1);
+    assert_err!(set.resize(0x4000.into(), usize::MAX, &mut pt), InvalidParam);
+
+    // Invalid parameters.
+    assert_err!(set.resize(0x1800.into(), 0x1000, &mut pt), InvalidParam);
+    assert_err!(set.resize(0x1000.into(), 0, &mut pt), InvalidParam);
+}
+
+#[test]
+fn test_split_at() {
+    let mut set = MemorySet::<HugeBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x4000, 1, HugeBackend),
+        &mut pt,
+        false,
+    ));
+
+    assert_eq!(set.split_at(0x3000.into()), Ok(true));
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x3000), 1),
+            (va_range!(0x3000..0x5000), 1)
+        ]
+    );
+    assert!(pt[0x1000..0x5000].iter().all(|&f| f == 1));
+    set.check_invariants();
+
+    // On an existing boundary.
+    assert_eq!(set.split_at(0x3000.into()), Ok(false));
+    assert_eq!(set.split_at(0x1000.into()), Ok(false));
+    // Unmapped or misaligned.
+    assert_err!(set.split_at(0x5000.into()), InvalidParam);
+    assert_err!(set.split_at(0x0.into()), InvalidParam);
+    assert_err!(set.split_at(0x3800.into()), InvalidParam);
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_area_split_misaligned() {
+    let mut area = MemoryArea::new(0x1000.into(), 0x4000, 1, HugeBackend);
+    assert!(area.split(0x2800.into()).is_none());
+    assert!(area.split(0x1001.into()).is_none());
+    assert_eq!(area.va_range(), va_range!(0x1000..0x5000));
+
+    let right = area.split(0x2000.into()).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1000..0x2000));
+    assert_eq!(right.va_range(), va_range!(0x2000..0x5000));
+}
+
+#[test]
+fn test_update_flags() {
+    let mut pt = [0; MAX_ADDR];
+    let mut area = MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend);
+    assert_ok!(area.map_area(&mut pt));
+
+    assert_ok!(area.update_flags(3, &mut pt));
+    assert_eq!(area.flags(), 3);
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 3));
+    assert_eq!(pt[0xfff], 0);
+    assert_eq!(pt[0x3000], 0);
+}
+
+#[test]
+fn test_flags_mut() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    let area = set.find_mut(0x1000.into()).unwrap();
+    *area.flags_mut() |= 0x80;
+    assert_eq!(area.flags(), 0x81);
+    assert_eq!(set.flags_at(0x1000.into()), Some(0x81));
+    // The page table is not touched.
+    assert!(pt[0x1000..0x2000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_clone_area() {
+    let mut pt = [0; MAX_ADDR];
+    let area = MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend);
+    assert_ok!(area.map_area(&mut pt));
+
+    let cloned = area.clone();
+    assert_eq!(cloned.va_range(), area.va_range());
+    assert_eq!(cloned.flags(), area.flags());
+
+    // The page table is not touched, so mapping the clone again fails.
+    assert_err!(cloned.map_area(&mut pt), BadState(()));
+    let mut new_pt = [0; MAX_ADDR];
+    assert_ok!(cloned.map_area(&mut new_pt));
+    assert!(new_pt[0x1000..0x3000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_area_clip() {
+    let area = MemoryArea::new(0x2000.into(), 0x2000, 1, MockBackend);
+    // Straddles the left and the right boundaries.
+    assert_eq!(
+        area.clip(va_range!(0x1000..0x3000)),
+        Some(va_range!(0x2000..0x3000))
+    );
+    assert_eq!(
+        area.clip(va_range!(0x3800..0x8000)),
+        Some(va_range!(0x3800..0x4000))
+    );
+    assert_eq!(
+        area.clip(va_range!(0..0x8000)),
+        Some(va_range!(0x2000..0x4000))
+    );
+    assert_eq!(
+        area.clip(va_range!(0x2800..0x3000)),
+        Some(va_range!(0x2800..0x3000))
+    );
+    assert_eq!(area.clip(va_range!(0x1000..0x2000)), None);
+    assert_eq!(area.clip(va_range!(0x4000..0x5000)), None);
+}
+
+#[test]
+fn test_area_metadata() {
+    let mut set = MemorySet::<MockBackend, &str>::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::with_metadata(0x1000.into(), 0x4000, 1, MockBackend, "heap"),
+        &mut pt,
+        false,
+    ));
+    assert_eq!(*set.find(0x1000.into()).unwrap().metadata(), "heap");
+
+    // Split by unmapping and protecting: the metadata is cloned into each part.
+    assert_ok!(set.unmap(0x2000.into(), 0x1000, &mut pt));
+    assert_ok!(set.protect(0x3400.into(), 0x400, |_| Some(2), &mut pt));
+    assert_eq!(set.len(), 4);
+    assert!(set.iter().all(|area| *area.metadata() == "heap"));
+
+    // Areas with different metadata are not merged.
+    let mut area = set.find(0x3800.into()).unwrap().clone();
+    *area.metadata_mut() = "stack";
+    assert_eq!(*area.metadata(), "stack");
+    assert_ok!(set.protect(0x3400.into(), 0x400, |_| Some(1), &mut pt));
+    assert_ok!(set.unmap(0x3800.into(), 0x1800, &mut pt));
+    assert_ok!(set.map(area, &mut pt, false));
+    set.merge_adjacent();
+    let areas = set.iter().collect::<Vec<_>>();
+    assert_eq!(areas.len(), 3);
+    assert_eq!(areas[1].va_range(), va_range!(0x3000..0x3800));
+    assert_eq!(*areas[1].metadata(), "heap");
+    assert_eq!(*areas[2].metadata(), "stack");
+}
+
+#[test]
+fn test_remap_area() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A backend that moves page table entries on `remap` and records the
+    /// calls.
+    #[derive(Clone, Default)]
+    struct MoveBackend(Rc<RefCell<Vec<(VirtAddr, VirtAddr, usize)>>>);
+
+    impl MappingBackend for MoveBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = ();
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.map(start, size, flags, pt)
+        }
+
+        fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
+            MockBackend.unmap(start, size, pt)
+        }
+
+        fn protect(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.protect(start, size, flags, pt)
+        }
+
+        fn remap(
+            &self,
+            old_start: VirtAddr,
+            new_start: VirtAddr,
+            size: usize,
+            _flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            self.0.borrow_mut().push((old_start, new_start, size));
+            let old = old_start.as_usize();
+            pt.copy_within(old..old + size, new_start.as_usize());
+            let new_range = va_range!(new_start.as_usize()..new_start.as_usize() + size);
+            for (addr, entry) in (old..old + size).zip(&mut pt[old..old + size]) {
+                if !new_range.contains(addr.into()) {
+                    *entry = 0;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    let backend = MoveBackend::default();
+    let mut set = MemorySet::<MoveBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0x1000, 0x3000), [0x8000, 0x9000).
+    for (start, size) in [(0x1000, 0x2000), (0x8000, 0x1000)] {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), size, 1, backend.clone()),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // Move to an overlapping range.
+    assert_ok!(set.remap_area(0x1000.into(), 0x2000.into(), &mut pt));
+    assert_eq!(
+        set.find(0x2000.into()).unwrap().va_range(),
+        va_range!(0x2000..0x4000)
+    );
+    assert!(set.find(0x1000.into()).is_none());
+    assert_eq!(pt[0x1fff], 0);
+    assert!(pt[0x2000..0x4000].iter().all(|&f| f == 1));
+
+    // Move to a disjoint range.
+    assert_ok!(set.remap_area(0x2000.into(), 0x5000.into(), &mut pt));
+    assert_eq!(
+        set.find(0x5000.into()).unwrap().va_range(),
+        va_range!(0x5000..0x7000)
+    );
+    assert!(pt[0x2000..0x5000].iter().all(|&f| f == 0));
+    assert!(pt[0x5000..0x7000].iter().all(|&f| f == 1));
+    assert_eq!(
+        *backend.0.borrow(),
+        [
+            (0x1000.into(), 0x2000.into(), 0x2000),
+            (0x2000.into(), 0x5000.into(), 0x2000),
+        ]
+    );
+
+    // Invalid moves do not call the backend.
+    assert_err!(
+        set.remap_area(0x5000.into(), 0x7800.into(), &mut pt),
+        AlreadyExists
+    );
+    assert_err!(
+        set.remap_area(0x1000.into(), 0x2000.into(), &mut pt),
+        InvalidParam
+    );
+    assert_err!(
+        set.remap_area(0x5000.into(), usize::MAX.into(), &mut pt),
+        InvalidParam
+    );
+    assert_eq!(backend.0.borrow().len(), 2);
+    assert_eq!(set.len(), 2);
+    set.check_invariants();
+
+    // The default implementation unmaps and then maps.
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    assert_ok!(set.remap_area(0x1000.into(), 0x1800.into(), &mut pt));
+    assert_eq!(
+        set.iter().next().unwrap().va_range(),
+        va_range!(0x1800..0x3800)
+    );
+    assert!(pt[0x1000..0x1800].iter().all(|&f| f == 0));
+    assert!(pt[0x1800..0x3800].iter().all(|&f| f == 1));
+
+    // If mapping the new range fails, the old range is mapped back.
+    pt[0x5000] = 9;
+    assert_err!(
+        set.remap_area(0x1800.into(), 0x5000.into(), &mut pt),
+        BadState(())
+    );
+    assert_eq!(
+        set.iter().next().unwrap().va_range(),
+        va_range!(0x1800..0x3800)
+    );
+    assert!(pt[0x1800..0x3800].iter().all(|&f| f == 1));
+    assert!(pt[0x5001..0x7000].iter().all(|&f| f == 0));
+    set.check_invariants();
+}
+
+#[test]
+fn test_backend_error() {
+    #[derive(Debug, PartialEq)]
+    enum FrameError {
+        OutOfFrames,
+    }
+
+    /// A backend that has no frames to map.
+    #[derive(Clone)]
+    struct NoFrameBackend;
+
+    impl MappingBackend for NoFrameBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = FrameError;
+
+        fn map(
+            &self,
+            _: VirtAddr,
+            _: usize,
+            _: MockFlags,
+            _: &mut MockPageTable,
+        ) -> Result<(), FrameError> {
+            Err(FrameError::OutOfFrames)
+        }
+
+        fn unmap(&self, _: VirtAddr, _: usize, _: &mut MockPageTable) -> Result<(), FrameError> {
+            Ok(())
+        }
+
+        fn protect(
+            &self,
+            _: VirtAddr,
+            _: usize,
+            _: MockFlags,
+            _: &mut MockPageTable,
+        ) -> Result<(), FrameError> {
+            Ok(())
+        }
+    }
+
+    let mut set = MemorySet::<NoFrameBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x1000.into(), 0x1000, 1, NoFrameBackend),
+            &mut pt,
+            false,
+        ),
+        BadState(FrameError::OutOfFrames)
+    );
+    assert!(set.is_empty());
+}
+
+#[test]
+fn test_protect_error() {
+    /// A backend that can not change the flags.
+    #[derive(Clone)]
+    struct ReadOnlyBackend;
+
+    impl MappingBackend for ReadOnlyBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = ();
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.map(start, size, flags, pt)
+        }
+
+        fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
+            MockBackend.unmap(start, size, pt)
+        }
+
+        fn protect(
+            &self,
+            _: VirtAddr,
+            _: usize,
+            _: MockFlags,
+            _: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            Err(())
+        }
+    }
+
+    let mut set = MemorySet::<ReadOnlyBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x3000, 1, ReadOnlyBackend),
+        &mut pt,
+        false,
+    ));
+
+    assert_err!(
+        set.protect(0x1000.into(), 0x3000, |_| Some(2), &mut pt),
+        BadState(())
+    );
+    assert_eq!(set.len(), 1);
+    assert_eq!(set.flags_at(0x1000.into()), Some(1));
+
+    // The area is split, but no part is lost and the flags are unchanged.
+    assert_err!(
+        set.protect(0x2000.into(), 0x1000, |_| Some(2), &mut pt),
+        BadState(())
+    );
+    assert_eq!(set.mapped_size_in(va_range!(0..MAX_ADDR)), 0x3000);
+    assert!(set.layout().all(|(_, flags)| flags == 1));
+    set.check_invariants();
+
+    assert_err!(set.protect_all(|_| Some(2), &mut pt), BadState(()));
+    let area = set.find_mut(0x1000.into()).unwrap();
+    assert_err!(area.update_flags(2, &mut pt), BadState(()));
+    assert_eq!(area.flags(), 1);
+    assert!(pt[0x1000..0x4000].iter().all(|&f| f == 1));
+}
+
+#[test]
+fn test_map_each() {
+    let mut pt = [0; MAX_ADDR];
+
+    // The default maps the whole range at once.
+    assert_ok!(MockBackend.map_each(va_range!(0x1000..0x4000), 1, &mut pt));
+    assert!(pt[0x1000..0x4000].iter().all(|&f| f == 1));
+    assert_ok!(MockBackend.unmap(0x1000.into(), 0x3000, &mut pt));
+
+    // Map page by page.
+    assert_ok!(HugeBackend.map_each_page(va_range!(0x1000..0x4000), 1, &mut pt));
+    assert!(pt[0x1000..0x4000].iter().all(|&f| f == 1));
+    assert_ok!(HugeBackend.unmap(0x1000.into(), 0x3000, &mut pt));
+
+    // Mapping the third page fails, the first two pages are rolled back.
+    pt[0x3000] = 2;
+    assert_err!(HugeBackend.map_each_page(va_range!(0x1000..0x5000), 1, &mut pt));
+    assert!(pt[0x1000..0x3000].iter().all(|&f| f == 0));
+    assert_eq!(pt[0x3000], 2);
+    assert!(pt[0x3001..0x5000].iter().all(|&f| f == 0));
+}
+
+#[test]
+fn test_map_each_override() {
+    use crate::testing::{Op, RecordLog, RecordingBackend};
+
+    /// A backend that maps each page individually.
+    #[derive(Clone)]
+    struct PerPageBackend(RecordingBackend<VirtAddr, MockFlags>);
+
+    impl MappingBackend for PerPageBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = RecordLog<VirtAddr, MockFlags>;
+        type Error = ();
+
+        fn page_size(&self) -> usize {
+            self.0.page_size()
+        }
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            log: &mut Self::PageTable,
+        ) -> Result<(), ()> {
+            self.0.map(start, size, flags, log)
+        }
+
+        fn unmap(&self, start: VirtAddr, size: usize, log: &mut Self::PageTable) -> Result<(), ()> {
+            self.0.unmap(start, size, log)
+        }
+
+        fn protect(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            new_flags: MockFlags,
+            log: &mut Self::PageTable,
+        ) -> Result<(), ()> {
+            self.0.protect(start, size, new_flags, log)
+        }
+
+        fn map_each(
+            &self,
+            range: VirtAddrRange,
+            flags: MockFlags,
+            log: &mut Self::PageTable,
+        ) -> Result<(), ()> {
+            self.map_each_page(range, flags, log)
+        }
+    }
+
+    let backend = PerPageBackend(RecordingBackend::new(0x1000));
+    let mut set = MemorySet::new();
+    let mut log = Vec::new();
+
+    // `map` and `resize` go through `map_each`.
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, backend.clone()),
+        &mut log,
+        false,
+    ));
+    assert_ok!(set.resize(0x1000.into(), 0x4000, &mut log));
+    assert_eq!(
+        log,
+        [
+            (Op::Map, 0x1000.into(), 0x1000, Some(1)),
+            (Op::Map, 0x2000.into(), 0x1000, Some(1)),
+            (Op::Map, 0x3000.into(), 0x1000, Some(1)),
+            (Op::Map, 0x4000.into(), 0x1000, Some(1)),
+        ]
+    );
+}
+
+#[test]
+fn test_area_try_new() {
+    // Any alignment is accepted with the default page size.
+    let area = MemoryArea::try_new(0x1001.into(), 0x123, 1, MockBackend).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x1001..0x1124));
+
+    // Near the top of the address space.
+    let top = VirtAddr::from(usize::MAX - 0xfff);
+    let area = MemoryArea::try_new(top, 0xfff, 1, MockBackend).unwrap();
+    assert_eq!(area.end(), usize::MAX.into());
+    assert_err!(
+        MemoryArea::try_new(top, 0x1000, 1, MockBackend),
+        InvalidParam
+    );
+    assert_err!(
+        MemoryArea::try_new(1.into(), usize::MAX, 1, MockBackend),
+        InvalidParam
+    );
+
+    let area = MemoryArea::try_new(0x2000.into(), 0x3000, 1, HugeBackend).unwrap();
+    assert_eq!(area.va_range(), va_range!(0x2000..0x5000));
+    assert_err!(
+        MemoryArea::try_new(0x2800.into(), 0x3000, 1, HugeBackend),
+        InvalidParam
+    );
+    assert_err!(
+        MemoryArea::try_new(0x2000.into(), 0x2800, 1, HugeBackend),
+        InvalidParam
+    );
+    assert_err!(
+        MemoryArea::try_new(0x1000.into(), usize::MAX & !0xfff, 1, HugeBackend),
+        InvalidParam
+    );
+}
+
+#[test]
+fn test_area_builder() {
+    let area = MemoryArea::builder(HugeBackend)
+        .start(0x2000.into())
+        .size(0x3000)
+        .flags(1)
+        .build()
+        .unwrap();
+    assert_eq!(area.va_range(), va_range!(0x2000..0x5000));
+    assert_eq!(area.flags(), 1);
+
+    // Misaligned start or size.
+    assert_err!(
+        MemoryArea::builder(HugeBackend)
+            .start(0x2800.into())
+            .size(0x3000)
+            .flags(1)
+            .build(),
+        InvalidParam
+    );
+    assert_err!(
+        MemoryArea::builder(HugeBackend)
+            .start(0x2000.into())
+            .size(0x2800)
+            .flags(1)
+            .build(),
+        InvalidParam
+    );
+    // Overflow.
+    assert_err!(
+        MemoryArea::builder(MockBackend)
+            .start(1.into())
+            .size(usize::MAX)
+            .flags(1)
+            .build(),
+        InvalidParam
+    );
+    // Missing fields.
+    assert_err!(
+        MemoryArea::builder(MockBackend)
+            .start(0x1000.into())
+            .flags(1)
+            .build(),
+        InvalidParam
+    );
+}
+
+#[test]
+fn test_top_of_address_space() {
+    use crate::testing::{Op, RecordingBackend};
+
+    // The mock page table can not hold entries near the top of the address
+    // space, so the backend calls are recorded instead.
+    let backend = RecordingBackend::<VirtAddr, MockFlags>::new(HUGE_PAGE_SIZE);
+    let mut set = MemorySet::new();
+    let mut log = Vec::new();
+
+    // The top page can not be represented.
+    let top_page = VirtAddr::from(usize::MAX - 0xfff);
+    assert_err!(
+        MemoryArea::try_new(top_page, HUGE_PAGE_SIZE, 1, backend),
+        InvalidParam
+    );
+
+    // The page right below it is the highest one that can be mapped.
+    let last_page = top_page - HUGE_PAGE_SIZE;
+    let area = MemoryArea::try_new(last_page, HUGE_PAGE_SIZE, 1, backend).unwrap();
+    assert_eq!(area.end(), top_page);
+    assert_ok!(set.map(area, &mut log, false));
+    assert!(set.find(top_page - 1).is_some());
+    assert!(set.find(top_page).is_none());
+    assert_ok!(set.protect(last_page, HUGE_PAGE_SIZE, |_| Some(2), &mut log));
+    assert_eq!(set.flags_at(top_page - 1), Some(2));
+
+    // Ranges running past the end are rejected rather than overflowing.
+    assert_err!(
+        set.unmap(last_page, 2 * HUGE_PAGE_SIZE, &mut log),
+        InvalidParam
+    );
+    assert_err!(
+        set.protect(top_page, HUGE_PAGE_SIZE, |_| Some(3), &mut log),
+        InvalidParam
+    );
+    assert_eq!(set.flags_at(top_page - 1), Some(2));
+    assert_ok!(set.unmap(last_page, HUGE_PAGE_SIZE, &mut log));
+    assert!(set.is_empty());
+    assert_eq!(
+        log,
+        [
+            (Op::Map, last_page, HUGE_PAGE_SIZE, Some(1)),
+            (Op::Protect, last_page, HUGE_PAGE_SIZE, Some(2)),
+            (Op::Unmap, last_page, HUGE_PAGE_SIZE, None),
+        ]
+    );
+}
+
+#[test]
+fn test_map_misaligned_huge_page() {
+    let mut set = MemorySet::<HugeBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Misaligned start or size.
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x1800.into(), 0x1000, 1, HugeBackend),
+            &mut pt,
+            false,
+        ),
+        InvalidParam
+    );
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x1000.into(), 0x1800, 1, HugeBackend),
+            &mut pt,
+            false,
+        ),
+        InvalidParam
+    );
+    assert!(set.is_empty());
+    assert!(pt.iter().all(|&f| f == 0));
+
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x2000, 1, HugeBackend),
+        &mut pt,
+        false,
+    ));
+    assert_eq!(set.len(), 1);
+}
+
+#[test]
+fn test_misaligned_huge_page_ops() {
+    let mut set = MemorySet::<HugeBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x4000, 1, HugeBackend),
+        &mut pt,
+        false,
+    ));
+
+    // Cutting the area at a misaligned address is rejected before the backend
+    // is called.
+    assert_err!(set.unmap(0x1800.into(), 0x800, &mut pt), InvalidParam);
+    assert_err!(set.unmap_count(0x2000.into(), 0x800, &mut pt), InvalidParam);
+    assert_err!(
+        set.unmap_collect(0x1800.into(), 0x4000, &mut pt),
+        InvalidParam
+    );
+    assert_err!(
+        set.unmap_where(va_range!(0x1800..0x3000), |_| true, &mut pt),
+        InvalidParam
+    );
+    assert_err!(
+        set.protect(0x2100.into(), 0x100, |_| Some(2), &mut pt),
+        InvalidParam
+    );
+    assert_err!(
+        set.protect_reporting(0x2000.into(), 0x100, |_| Some(2), &mut pt),
+        InvalidParam
+    );
+    assert_err!(
+        set.protect_bounded(0x2100.into(), 0x100, |_| Some(2), 2, &mut pt),
+        InvalidParam
+    );
+    assert_err!(set.resize(0x1000.into(), 0x123, &mut pt), InvalidParam);
+    assert_err!(set.resize(0x1000.into(), 0x4800, &mut pt), InvalidParam);
+    assert_err!(
+        set.remap_area(0x1000.into(), 0x8080.into(), &mut pt),
+        InvalidParam
+    );
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [(va_range!(0x1000..0x5000), 1)]
+    );
+    assert!(pt[0x1000..0x5000].iter().all(|&f| f == 1));
+
+    // Misaligned boundaries outside of any area do not matter.
+    assert_ok!(set.protect(0x800.into(), 0x1800, |_| Some(2), &mut pt));
+    assert_ok!(set.unmap(0x4000.into(), 0x1800, &mut pt));
+    assert_eq!(
+        set.layout().collect::<Vec<_>>(),
+        [
+            (va_range!(0x1000..0x2000), 2),
+            (va_range!(0x2000..0x4000), 1)
+        ]
+    );
+    set.check_invariants();
+}
+
+#[test]
+fn test_finish_batch() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A backend that records the calls to `finish_batch`.
+    #[derive(Clone, Default)]
+    struct FlushBackend(Rc<RefCell<Vec<VirtAddrRange>>>);
+
+    impl MappingBackend for FlushBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = ();
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.map(start, size, flags, pt)
+        }
+
+        fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
+            MockBackend.unmap(start, size, pt)
+        }
+
+        fn protect(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.protect(start, size, flags, pt)
+        }
+
+        fn finish_batch(&self, affected: VirtAddrRange, _pt: &mut MockPageTable) {
+            self.0.borrow_mut().push(affected);
+        }
+    }
+
+    let backend = FlushBackend::default();
+    let mut set = MemorySet::<FlushBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+
+    // Map [0, 0x1000), [0x2000, 0x3000), [0x4000, 0x5000), ...
+    for start in (0..MAX_ADDR).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, backend.clone()),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(backend.0.borrow().len(), 8);
+    assert_eq!(backend.0.borrow()[1], va_range!(0x2000..0x3000));
+    backend.0.borrow_mut().clear();
+
+    // One call for an operation across several areas.
+    assert_ok!(set.protect(0x800.into(), 0x4000, |_| Some(2), &mut pt));
+    assert_eq!(*backend.0.borrow(), [va_range!(0x800..0x4800)]);
+    assert_ok!(set.unmap(0x2800.into(), 0x4000, &mut pt));
+    assert_eq!(backend.0.borrow()[1], va_range!(0x2800..0x6800));
+
+    // Mapping with `unmap_overlap` is still one operation.
+    assert_ok!(set.map(
+        MemoryArea::new(0x8000.into(), 0x4000, 3, backend.clone()),
+        &mut pt,
+        true,
+    ));
+    assert_eq!(backend.0.borrow().len(), 3);
+    assert_eq!(backend.0.borrow()[2], va_range!(0x8000..0xc000));
+
+    // No call if nothing is affected.
+    assert_ok!(set.unmap(0x3000.into(), 0x800, &mut pt));
+    assert_ok!(set.protect(0x3000.into(), 0x800, |_| Some(2), &mut pt));
+    assert_ok!(set.protect(0.into(), 0x3000, |_| None, &mut pt));
+    assert_ok!(set.protect_all(|_| None, &mut pt));
+    assert_eq!(backend.0.borrow().len(), 3);
+    backend.0.borrow_mut().clear();
+
+    // The other operations that change the page table.
+    assert_ok!(set.resize(0xc000.into(), 0x1800, &mut pt));
+    assert_ok!(set.resize(0xc000.into(), 0x1000, &mut pt));
+    assert_ok!(set.remap_area(0xe000.into(), 0xd800.into(), &mut pt));
+    assert_ok!(set.protect_all(|f| (f == 1).then_some(4), &mut pt));
+    assert_ok!(set.retain_areas(|area| area.flags() != 2, &mut pt));
+    assert_ok!(set.retain_areas(|area| area.flags() != 2, &mut pt));
+    assert_ok!(set.clear(&mut pt));
+    assert_ok!(set.clear(&mut pt));
+    assert_eq!(
+        *backend.0.borrow(),
+        [
+            va_range!(0xd000..0xd800),
+            va_range!(0xd000..0xd800),
+            va_range!(0xd800..0xf000),
+            va_range!(0..0xe800),
+            va_range!(0x800..0x2800),
+            va_range!(0..0xe800),
+        ]
+    );
+}
+
+#[test]
+fn test_recording_backend() {
+    use crate::testing::{Op, RecordingBackend};
+
+    let backend = RecordingBackend::<VirtAddr, MockFlags>::new(0x1000);
+    let mut set = MemorySet::new();
+    let mut log = Vec::new();
+
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x4000, 1, backend),
+        &mut log,
+        false,
+    ));
+    assert_ok!(set.protect(0x2000.into(), 0x1000, |_| Some(2), &mut log));
+    assert_ok!(set.unmap(0.into(), 0x3000, &mut log));
+    assert_eq!(
+        log,
+        [
+            (Op::Map, 0x1000.into(), 0x4000, Some(1)),
+            (Op::Protect, 0x2000.into(), 0x1000, Some(2)),
+            (Op::Unmap, 0x1000.into(), 0x1000, None),
+            (Op::Unmap, 0x2000.into(), 0x1000, None),
+        ]
+    );
+
+    // The page size is honored.
+    assert_err!(
+        set.map(
+            MemoryArea::new(0x8000.into(), 0x800, 1, backend),
+            &mut log,
+            false,
+        ),
+        InvalidParam
+    );
+    assert_eq!(log.len(), 4);
+}
+
+#[test]
+fn test_find_free_area() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    let limit = va_range!(0x1000..0xf000);
+
+    // An empty set.
+    assert_eq!(
+        set.find_free_area(0.into(), 0x1000, limit, 0x1000),
+        Some(0x1000.into())
+    );
+    assert_eq!(
+        set.find_free_area(0x1234.into(), 0x1000, limit, 0x800),
+        Some(0x1800.into())
+    );
+    assert_eq!(
+        set.find_free_area(0x1234.into(), 0x1000, limit, 0x10000),
+        None
+    );
+    assert_eq!(
+        set.find_free_area(0.into(), 0x1000, va_range!(0x1000..MAX_ADDR * 2), 0x10000),
+        Some(0x10000.into())
+    );
+
+    // Map [0x1000, 0x2100), [0x3000, 0x4100), [0x5000, 0x6100), ...
+    for start in (0x1000..0xf000).step_by(0x2000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1100, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+
+    // The gap [0x2100, 0x3000) is large enough only without alignment.
+    assert_eq!(
+        set.find_free_area(0.into(), 0x800, limit, 0x1),
+        Some(0x2100.into())
+    );
+    assert_eq!(
+        set.find_free_area(0.into(), 0x800, limit, 0x800),
+        Some(0x2800.into())
+    );
+    assert_eq!(set.find_free_area(0.into(), 0x900, limit, 0x800), None);
+    // Areas before the hint are skipped.
+    assert_eq!(
+        set.find_free_area(0x6000.into(), 0x800, limit, 0x800),
+        Some(0x6800.into())
+    );
+    // The area should not exceed the limit.
+    assert_eq!(
+        set.find_free_area(0.into(), 0x800, va_range!(0x1000..0x2800), 0x800),
+        None
+    );
+    // The first 64K-aligned free address is after the last area.
+    assert_eq!(
+        set.find_free_area(0x1000.into(), 0x1000, va_range!(0..MAX_ADDR * 2), 0x10000),
+        Some(0x10000.into())
+    );
+    assert_eq!(
+        set.find_free_area(0x1000.into(), 0x1000, va_range!(0..MAX_ADDR), 0x10000),
+        None
+    );
+
+    // Rounding up near the top of the address space does not overflow.
+    let top = va_range!(usize::MAX - 0x1000..usize::MAX);
+    assert_eq!(set.find_free_area(top.start, 0x10, top, 0x10000), None);
+}
+
+#[test]
+fn test_map_alloc() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    let limit = va_range!(0x1000..0x8000);
+
+    assert_ok!(set.map(
+        MemoryArea::new(0x3000.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    // Two consecutive allocations do not overlap.
+    let first = set.map_alloc(0x1800, 0x1000, 2, MockBackend, limit, &mut pt);
+    assert_eq!(first, Ok(0x1000.into()));
+    let second = set.map_alloc(0x1000, 0x1000, 3, MockBackend, limit, &mut pt);
+    assert_eq!(second, Ok(0x4000.into()));
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.find(0x1000.into()).unwrap().size(), 0x1800);
+    assert_eq!(set.find(0x4000.into()).unwrap().flags(), 3);
+    assert_eq!(pt[0x27ff], 2);
+    assert_eq!(pt[0x2800], 0);
+    assert_eq!(pt[0x4fff], 3);
+
+    // Exhaust the limit.
+    assert_eq!(
+        set.map_alloc(0x3000, 0x1000, 4, MockBackend, limit, &mut pt),
+        Ok(0x5000.into())
+    );
+    assert_err!(
+        set.map_alloc(0x1000, 0x1000, 5, MockBackend, limit, &mut pt),
+        NoMemory
+    );
+    // The small gap [0x2800, 0x3000) is still available.
+    assert_eq!(
+        set.map_alloc(0x800, 0x800, 5, MockBackend, limit, &mut pt),
+        Ok(0x2800.into())
+    );
+    assert_err!(
+        set.map_alloc(0x1, 0x1, 6, MockBackend, limit, &mut pt),
+        NoMemory
+    );
+    assert_eq!(set.len(), 5);
+}
+
+#[test]
+fn test_map_alloc_with() {
+    /// A backend that records the address it was built for.
+    #[derive(Clone)]
+    struct PlacedBackend(VirtAddr);
+
+    impl MappingBackend for PlacedBackend {
+        type Addr = VirtAddr;
+        type Flags = MockFlags;
+        type PageTable = MockPageTable;
+        type Error = ();
+
+        fn map(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.map(start, size, flags, pt)
+        }
+
+        fn unmap(&self, start: VirtAddr, size: usize, pt: &mut MockPageTable) -> Result<(), ()> {
+            MockBackend.unmap(start, size, pt)
+        }
+
+        fn protect(
+            &self,
+            start: VirtAddr,
+            size: usize,
+            new_flags: MockFlags,
+            pt: &mut MockPageTable,
+        ) -> Result<(), ()> {
+            MockBackend.protect(start, size, new_flags, pt)
+        }
+    }
+
+    let mut set = MemorySet::<PlacedBackend>::new();
+    let mut pt = [0; MAX_ADDR];
+    let limit = va_range!(0x1000..0x8000);
+    assert_ok!(set.map(
+        MemoryArea::new(0x1000.into(), 0x1000, 1, PlacedBackend(0x1000.into())),
+        &mut pt,
+        false,
+    ));
+
+    let start = set
+        .map_alloc_with(0x2000, 0x1000, 2, limit, &mut pt, PlacedBackend)
+        .unwrap();
+    assert_eq!(start, 0x2000.into());
+    assert_eq!(set.find(start).unwrap().backend().0, start);
+    assert!(pt[0x2000..0x4000].iter().all(|&f| f == 2));
+
+    // `make_backend` is not called if there is no free area.
+    assert_err!(
+        set.map_alloc_with(0x8000, 0x1000, 3, limit, &mut pt, |_| unreachable!()),
+        NoMemory
+    );
+}
+
+#[test]
+fn test_find_free_area_top_down() {
+    let mut set = MockMemorySet::new();
+    let mut pt = [0; MAX_ADDR];
+    let limit = va_range!(0x1000..0xf000);
+
+    // An empty set: the topmost fit in the limit.
+    assert_eq!(
+        set.find_free_area_top_down(0x1000, 0x1000, limit),
+        Some(0xe000.into())
+    );
+    assert_eq!(
+        set.find_free_area_top_down(0x800, 0x1000, limit),
+        Some(0xe000.into())
+    );
+    assert_eq!(
+        set.find_free_area_top_down(0x800, 0x100, limit),
+        Some(0xe800.into())
+    );
+    assert_eq!(
+        set.find_free_area_top_down(0xe000, 0x1000, limit),
+        Some(0x1000.into())
+    );
+    assert_eq!(set.find_free_area_top_down(0xe001, 0x1, limit), None);
+
+    // Map [0x1000, 0x3000), [0x4000, 0x6000), [0x7000, 0x9000), ..., and
+    // [0xd000, 0x10000) which exceeds the limit.
+    for start in (0x1000..0xd000).step_by(0x3000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x2000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_ok!(set.map(
+        MemoryArea::new(0xd000.into(), 0x3000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+
+    // The topmost gap is [0xc000, 0xd000).
+    assert_eq!(
+        set.find_free_area_top_down(0x1000, 0x1000, limit),
+        Some(0xc000.into())
+    );
+    assert_eq!(
+        set.find_free_area_top_down(0x400, 0x400, limit),
+        Some(0xcc00.into())
+    );
+    // No gap is large enough.
+    assert_eq!(set.find_free_area_top_down(0x1001, 0x1, limit), None);
+    // The gap is large enough but cannot be aligned.
+    assert_eq!(set.find_free_area_top_down(0x800, 0x8000, limit), None);
+    // The gap is below the limit.
+    assert_eq!(
+        set.find_free_area_top_down(0x1000, 0x1000, va_range!(0x5000..0xc000)),
+        Some(0x9000.into())
+    );
+
+    // Fill the gap below the first area and all other gaps.
+    assert_ok!(set.map(
+        MemoryArea::new(0.into(), 0x1000, 1, MockBackend),
+        &mut pt,
+        false,
+    ));
+    for start in (0x3000..0xd000).step_by(0x3000) {
+        assert_ok!(set.map(
+            MemoryArea::new(start.into(), 0x1000, 1, MockBackend),
+            &mut pt,
+            false,
+        ));
+    }
+    assert_eq!(set.find_free_area_top_down(0x1, 0x1, limit), None);
+    assert_eq!(
+        set.find_free_area_top_down(0x1, 0x1, va_range!(0..MAX_ADDR)),
+        None
+    );
+}
+
+mod proptests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    /// The granularity of generated addresses and sizes.
+    const GRANULE: usize = 0x100;
+
+    #[derive(Clone, Debug)]
+    enum Op {
+        Map {
+            start: usize,
+            size: usize,
+            flags: MockFlags,
+            unmap_overlap: bool,
+        },
+        Unmap {
+            start: usize,
+            size: usize,
+        },
+        Protect {
+            start: usize,
+            size: usize,
+            flags: MockFlags,
+        },
+    }
+
+    /// Generates a granule-aligned `(start, size)` pair within `[0, MAX_ADDR)`.
+    fn range_strategy() -> impl Strategy<Value = (usize, usize)> {
+        (0..MAX_ADDR / GRANULE, 0..=0x40usize).prop_map(|(start, size)| {
+            let start = start * GRANULE;
+            (start, (size * GRANULE).min(MAX_ADDR - start))
+        })
+    }
+
+    fn op_strategy() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (range_strategy(), 1..=7u8, any::<bool>()).prop_map(
+                |((start, size), flags, unmap_overlap)| Op::Map {
+                    start,
+                    size,
+                    flags,
+                    unmap_overlap,
+                }
+            ),
+            range_strategy().prop_map(|(start, size)| Op::Unmap { start, size }),
+            (range_strategy(), 1..=7u8).prop_map(|((start, size), flags)| Op::Protect {
+                start,
+                size,
+                flags
+            }),
+        ]
+    }
+
+    fn apply(set: &mut MockMemorySet, pt: &mut MockPageTable, op: &Op) {
+        // Errors are expected for some operations (e.g., mapping an empty or
+        // overlapped area), and the set should be left consistent anyway.
+        let _ = match *op {
+            Op::Map {
+                start,
+                size,
+                flags,
+                unmap_overlap,
+            } => set.map(
+                MemoryArea::new(start.into(), size, flags, MockBackend),
+                pt,
+                unmap_overlap,
+            ),
+            Op::Unmap { start, size } => set.unmap(start.into(), size, pt),
+            Op::Protect { start, size, flags } => {
+                set.protect(start.into(), size, |_| Some(flags), pt)
+            }
+        };
+    }
+
+    /// Cross-checks the flat page table against the area list.
+    fn check_page_table(set: &MockMemorySet, pt: &MockPageTable) {
+        set.check_invariants();
+        for granule in (0..MAX_ADDR).step_by(GRANULE) {
+            let expected = set.find(granule.into()).map_or(0, |area| area.flags());
+            for addr in [granule, granule + GRANULE - 1] {
+                assert_eq!(pt[addr], expected, "page table mismatch at {addr:#x}");
+            }
+        }
+    }
+
+    fn layout(set: &MockMemorySet) -> Vec<(VirtAddrRange, MockFlags)> {
+        set.iter()
+            .map(|area| (area.va_range(), area.flags()))
+            .collect()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn prop_random_ops(ops in prop::collection::vec(op_strategy(), 1..32)) {
+            let mut set = MockMemorySet::new();
+            let mut pt = [0; MAX_ADDR];
+            for op in &ops {
+                apply(&mut set, &mut pt, op);
+                check_page_table(&set, &pt);
+            }
+        }
+
+        #[test]
+        fn prop_unmap_remap(
+            ops in prop::collection::vec(op_strategy(), 1..32),
+            (start, size) in range_strategy(),
+        ) {
+            let mut set = MockMemorySet::new();
+            let mut pt = [0; MAX_ADDR];
+            for op in &ops {
+                apply(&mut set, &mut pt, op);
+            }
+            let old_pt = pt;
+            let range = VirtAddrRange::from_start_size(start.into(), size);
+            let snapshot = set.snapshot_flags(range);
+
+            prop_assert!(set.unmap(start.into(), size, &mut pt).is_ok());
+            check_page_table(&set, &pt);
+            prop_assert!(range.is_empty() || !set.overlaps(range));
+
+            // Re-map the unmapped pieces.
+            for (piece, flags) in snapshot {
+                let area = MemoryArea::new(piece.start, piece.size(), flags, MockBackend);
+                prop_assert!(set.map(area, &mut pt, false).is_ok());
+            }
+            check_page_table(&set, &pt);
+            prop_assert!(pt == old_pt);
+        }
+
+        #[test]
+        fn prop_protect_idempotent(
+            ops in prop::collection::vec(op_strategy(), 1..32),
+            (start, size) in range_strategy(),
+            flags in 1..=7u8,
+        ) {
+            let mut set = MockMemorySet::new();
+            let mut pt = [0; MAX_ADDR];
+            for op in &ops {
+                apply(&mut set, &mut pt, op);
+            }
+
+            prop_assert!(set.protect(start.into(), size, |_| Some(flags), &mut pt).is_ok());
+            check_page_table(&set, &pt);
+            let (old_layout, old_pt) = (layout(&set), pt);
+
+            prop_assert!(set.protect(start.into(), size, |_| Some(flags), &mut pt).is_ok());
+            check_page_table(&set, &pt);
+            prop_assert_eq!(layout(&set), old_layout);
+            prop_assert!(pt == old_pt);
+        }
+    }
+}